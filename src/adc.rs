@@ -1078,14 +1078,6 @@ adc_pins!(
     gpioc::PC3<Analog> => (ADC1, 13),
     gpioc::PC3<Analog> => (ADC2, 13),
     gpioc::PC3<Analog> => (ADC3, 13),
-    gpiof::PF10<Analog> => (ADC3, 8),
-    gpiof::PF3<Analog> => (ADC3, 9),
-    gpiof::PF4<Analog> => (ADC3, 14),
-    gpiof::PF5<Analog> => (ADC3, 15),
-    gpiof::PF6<Analog> => (ADC3, 4),
-    gpiof::PF7<Analog> => (ADC3, 5),
-    gpiof::PF8<Analog> => (ADC3, 6),
-    gpiof::PF9<Analog> => (ADC3, 7),
     Temperature => (ADC1, 16),
     Temperature => (ADC2, 16),
     Temperature => (ADC3, 16),
@@ -1096,3 +1088,16 @@ adc_pins!(
     Vref => (ADC2, 17),
     Vref => (ADC3, 17),
 );
+
+// ADC3 inputs on GPIOF are only bonded out on LQFP144 and larger packages.
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
+adc_pins!(
+    gpiof::PF10<Analog> => (ADC3, 8),
+    gpiof::PF3<Analog> => (ADC3, 9),
+    gpiof::PF4<Analog> => (ADC3, 14),
+    gpiof::PF5<Analog> => (ADC3, 15),
+    gpiof::PF6<Analog> => (ADC3, 4),
+    gpiof::PF7<Analog> => (ADC3, 5),
+    gpiof::PF8<Analog> => (ADC3, 6),
+    gpiof::PF9<Analog> => (ADC3, 7),
+);
@@ -35,18 +35,24 @@ mod common_pins {
     use crate::gpio::{
         gpioa::{PA11, PA12},
         gpiob::{PB12, PB13, PB5, PB6},
-        gpiod::{PD0, PD1},
     };
     use crate::pac::{CAN1, CAN2};
     // All STM32F4 models with CAN support these pins
     pins! {
         CAN1 => (PA12<9>, PA11<9>),
-        CAN1 => (PD1<9>, PD0<9>),
         CAN2 => (PB13<9>, PB12<9>),
         CAN2 => (PB6<9>, PB5<9>),
     }
 }
 
+// GPIOD is only bonded out on LQFP100 and larger packages
+#[cfg(not(feature = "lqfp64"))]
+mod pd1_pd0 {
+    use crate::gpio::gpiod::{PD0, PD1};
+    use crate::pac::CAN1;
+    pins! { CAN1 => (PD1<9>, PD0<9>), }
+}
+
 #[cfg(any(feature = "stm32f412", feature = "stm32f413", feature = "stm32f423"))]
 mod pb9_pb8_af8 {
     use crate::gpio::gpiob::{PB8, PB9};
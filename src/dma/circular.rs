@@ -0,0 +1,189 @@
+//! Circular DMA reception into a ring buffer drained by software.
+
+use core::slice;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_dma::StaticWriteBuffer;
+
+use super::traits::{Channel, DMASet, PeriAddress, Stream};
+use super::{config, ChannelX, DMAError, PeripheralToMemory, Transfer};
+
+/// A peripheral to memory stream in circular mode, read at the pace of the software.
+///
+/// The DMA keeps writing into the buffer and wraps around at its end. The read index is kept by
+/// software and the write index is derived from the stream's NDTR register, so the data between
+/// them can be read at any time, as one or two slices when the unread data wraps.
+///
+/// Overruns, where the DMA writes over data that was not read yet, are detected with the half
+/// transfer and transfer complete flags. The detection is reliable as long as less than one and a
+/// half buffer lengths were written since the buffer was last checked.
+pub struct CircBuffer<STREAM, PERIPHERAL, BUF, const CHANNEL: u8>
+where
+    STREAM: Stream,
+    PERIPHERAL: PeriAddress,
+{
+    transfer: Transfer<STREAM, PERIPHERAL, PeripheralToMemory, BUF, CHANNEL>,
+    buf_ptr: u32,
+    buf_len: usize,
+    read_index: usize,
+}
+
+impl<STREAM, PERIPHERAL, BUF, const CHANNEL: u8> CircBuffer<STREAM, PERIPHERAL, BUF, CHANNEL>
+where
+    STREAM: Stream,
+    ChannelX<CHANNEL>: Channel,
+    PERIPHERAL: PeriAddress + DMASet<STREAM, PeripheralToMemory, CHANNEL>,
+    BUF: StaticWriteBuffer<Word = <PERIPHERAL as PeriAddress>::MemSize>,
+{
+    /// Configures the DMA stream in circular mode with `buf` as the ring buffer. Memory increment
+    /// is always enabled and double buffering always disabled, the rest of `config` is applied as
+    /// is. The stream is started with [`start`](Self::start).
+    ///
+    /// # Panics
+    ///
+    /// * When the buffer length is odd or zero, the half transfer flag needs an even length.
+    pub fn new(
+        stream: STREAM,
+        peripheral: PERIPHERAL,
+        mut buf: BUF,
+        config: config::DmaConfig,
+    ) -> Self {
+        // NOTE(unsafe) The buffer is owned by the transfer until it is released
+        let (buf_ptr, buf_len) = unsafe { buf.write_buffer() };
+        assert!(
+            buf_len != 0 && buf_len % 2 == 0,
+            "Circular buffer length must be even"
+        );
+
+        let config = config.memory_increment(true).double_buffer(false);
+        let mut transfer =
+            Transfer::init_peripheral_to_memory(stream, peripheral, buf, None, config);
+        // NOTE(unsafe) Circular mode doesn't change the buffer the stream writes to
+        unsafe { transfer.get_stream() }.set_circular_mode(true);
+
+        Self {
+            transfer,
+            buf_ptr: buf_ptr as u32,
+            buf_len,
+            read_index: 0,
+        }
+    }
+
+    /// Starts the stream, the closure will be executed right after enabling the stream.
+    pub fn start<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        self.transfer.start(f)
+    }
+
+    /// Stops the stream and returns the underlying resources, the stream is left out of circular
+    /// mode.
+    pub fn release(self) -> (STREAM, PERIPHERAL, BUF) {
+        let (mut stream, peripheral, buf, _) = self.transfer.release();
+        stream.set_circular_mode(false);
+        (stream, peripheral, buf)
+    }
+
+    /// Returns the number of elements written by the DMA and not read yet.
+    ///
+    /// On overrun the unread data is discarded: the read index jumps to the write index and the
+    /// flags are cleared, so reading can continue with fresh data after the error.
+    pub fn available(&mut self) -> Result<usize, DMAError<()>> {
+        // The flags are sampled before NDTR, a boundary crossed in between then shows up as
+        // written data without its flag instead of a flag without its data
+        let half_transfer = STREAM::get_half_transfer_flag();
+        let transfer_complete = STREAM::get_transfer_complete_flag();
+        let write_index = self.write_index();
+
+        let available = if write_index >= self.read_index {
+            write_index - self.read_index
+        } else {
+            self.buf_len - self.read_index + write_index
+        };
+
+        // The flag of a boundary is cleared when the reader passes it, so a set flag belongs to a
+        // boundary in the unread data unless the DMA went around and passed it once more
+        let overrun = (half_transfer && self.distance_to(self.buf_len / 2) > available)
+            || (transfer_complete && self.distance_to(0) > available);
+        if overrun {
+            self.transfer.clear_half_transfer_interrupt();
+            self.transfer.clear_transfer_complete_interrupt();
+            self.read_index = self.write_index();
+            return Err(DMAError::Overrun(()));
+        }
+
+        Ok(available)
+    }
+
+    /// Returns the unread data as two slices, the second one is only non-empty when the data wraps
+    /// around the end of the buffer. Call [`consume`](Self::consume) once the data was processed.
+    pub fn readable(
+        &mut self,
+    ) -> Result<(&[PERIPHERAL::MemSize], &[PERIPHERAL::MemSize]), DMAError<()>> {
+        let available = self.available()?;
+        let first = available.min(self.buf_len - self.read_index);
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads"
+        compiler_fence(Ordering::Acquire);
+
+        let ptr = self.buf_ptr as *const PERIPHERAL::MemSize;
+        // NOTE(unsafe) Only the unread part of the buffer is borrowed, the DMA doesn't write
+        // there unless it overruns
+        unsafe {
+            Ok((
+                slice::from_raw_parts(ptr.add(self.read_index), first),
+                slice::from_raw_parts(ptr, available - first),
+            ))
+        }
+    }
+
+    /// Marks `count` elements as read, making room for the DMA.
+    ///
+    /// # Panics
+    ///
+    /// * When `count` is greater than the buffer length.
+    pub fn consume(&mut self, count: usize) {
+        assert!(count <= self.buf_len);
+
+        // Clear the flags of the boundaries passed by the reader
+        if self.distance_to(self.buf_len / 2) <= count {
+            self.transfer.clear_half_transfer_interrupt();
+        }
+        if self.distance_to(0) <= count {
+            self.transfer.clear_transfer_complete_interrupt();
+        }
+        self.read_index = (self.read_index + count) % self.buf_len;
+    }
+
+    /// Copies as much unread data as fits into `dst` and returns the number of elements copied.
+    ///
+    /// The overrun check is repeated after copying, so data overwritten during the copy is never
+    /// returned.
+    pub fn read(&mut self, dst: &mut [PERIPHERAL::MemSize]) -> Result<usize, DMAError<()>>
+    where
+        PERIPHERAL::MemSize: Copy,
+    {
+        let (first, second) = self.readable()?;
+        let n_first = first.len().min(dst.len());
+        let n_second = second.len().min(dst.len() - n_first);
+        dst[..n_first].copy_from_slice(&first[..n_first]);
+        dst[n_first..n_first + n_second].copy_from_slice(&second[..n_second]);
+
+        self.available()?;
+        self.consume(n_first + n_second);
+        Ok(n_first + n_second)
+    }
+
+    /// Index of the next element the DMA will write.
+    fn write_index(&self) -> usize {
+        // NDTR is reloaded as soon as it reaches zero in circular mode
+        (self.buf_len - STREAM::get_number_of_transfers() as usize) % self.buf_len
+    }
+
+    /// Number of elements to read before the reader passes `boundary`, from 1 to the buffer
+    /// length.
+    fn distance_to(&self, boundary: usize) -> usize {
+        (boundary + self.buf_len - self.read_index - 1) % self.buf_len + 1
+    }
+}
@@ -0,0 +1,453 @@
+//! Streams and transfers with the stream and the channel chosen at runtime.
+
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
+
+use super::traits::{is_mapped, sealed::Bits, Direction, Instance, PeriAddress};
+use super::{config, DMAError, MemoryToPeripheral, PeripheralToMemory, StreamX, StreamsTuple};
+use crate::pac;
+
+// Flags of a stream in the LISR/HISR and LIFCR/HIFCR registers, relative to the stream's offset
+const FEIF: u32 = 1 << 0;
+const DMEIF: u32 = 1 << 2;
+const TEIF: u32 = 1 << 3;
+const HTIF: u32 = 1 << 4;
+const TCIF: u32 = 1 << 5;
+const ALL_FLAGS: u32 = FEIF | DMEIF | TEIF | HTIF | TCIF;
+
+/// A stream of the DMA controller whose number is only known at runtime.
+///
+/// Created from a [`StreamX`] or handed out by a [`StreamsAllocator`], and used with an
+/// [`AnyTransfer`].
+pub struct AnyStream<DMA> {
+    number: u8,
+    _dma: PhantomData<DMA>,
+}
+
+impl<DMA: Instance, const S: u8> From<StreamX<DMA, S>> for AnyStream<DMA> {
+    fn from(_stream: StreamX<DMA, S>) -> Self {
+        Self::new(S)
+    }
+}
+
+impl<DMA: Instance> AnyStream<DMA> {
+    fn new(number: u8) -> Self {
+        Self {
+            number,
+            _dma: PhantomData,
+        }
+    }
+
+    /// Number of the stream.
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    /// Converts back to the typed stream, fails if the stream has another number.
+    pub fn into_stream<const S: u8>(self) -> Result<StreamX<DMA, S>, Self> {
+        if self.number == S {
+            Ok(StreamX::new())
+        } else {
+            Err(self)
+        }
+    }
+
+    #[inline(always)]
+    fn st(&self) -> &'static pac::dma2::ST {
+        // NOTE(unsafe) we only access the registers of the stream we own
+        unsafe { &(*DMA::ptr()).st[self.number as usize] }
+    }
+
+    /// Reads the flags of the stream, shifted to the stream's offset.
+    fn flags(&self) -> u32 {
+        // NOTE(unsafe) atomic read with no side effects
+        let dma = unsafe { &*DMA::ptr() };
+        let isr = if self.number < 4 {
+            dma.lisr.read().bits()
+        } else {
+            dma.hisr.read().bits()
+        };
+        (isr >> self.flags_offset()) & ALL_FLAGS
+    }
+
+    fn clear_flags(&mut self, flags: u32) {
+        //NOTE(unsafe) Atomic write with no side-effects and we only access the bits
+        // that belongs to the stream
+        let dma = unsafe { &*DMA::ptr() };
+        let bits = flags << self.flags_offset();
+        if self.number < 4 {
+            dma.lifcr.write(|w| unsafe { w.bits(bits) });
+        } else {
+            dma.hifcr.write(|w| unsafe { w.bits(bits) });
+        }
+    }
+
+    fn flags_offset(&self) -> u32 {
+        [0, 6, 16, 22][self.number as usize % 4]
+    }
+
+    /// Returns the state of the DMA stream.
+    pub fn is_enabled(&self) -> bool {
+        self.st().cr.read().en().bit_is_set()
+    }
+
+    /// Disables the DMA stream, waiting for the end of the on-going transfer and clearing the
+    /// interrupt flags if it was active.
+    pub fn disable(&mut self) {
+        if self.is_enabled() {
+            // Aborting an on-going transfer might cause interrupts to fire, disable them
+            let cr = self.st().cr.read().bits();
+            let interrupts = cr & 0b1_1110;
+            self.st()
+                .cr
+                .write(|w| unsafe { w.bits(cr & !interrupts & !1) });
+            while self.is_enabled() {}
+
+            self.clear_interrupts();
+            self.st()
+                .cr
+                .modify(|r, w| unsafe { w.bits(r.bits() | interrupts) });
+        }
+    }
+
+    /// Get the number of transfers (ndt) for the DMA stream.
+    pub fn get_number_of_transfers(&self) -> u16 {
+        self.st().ndtr.read().ndt().bits()
+    }
+
+    /// Clear all interrupts for the DMA stream.
+    pub fn clear_interrupts(&mut self) {
+        self.clear_flags(ALL_FLAGS);
+    }
+
+    /// Clear transfer complete interrupt (tcif) for the DMA stream.
+    pub fn clear_transfer_complete_interrupt(&mut self) {
+        self.clear_flags(TCIF);
+    }
+
+    /// Clear half transfer interrupt (htif) for the DMA stream.
+    pub fn clear_half_transfer_interrupt(&mut self) {
+        self.clear_flags(HTIF);
+    }
+
+    /// Get transfer complete flag.
+    pub fn get_transfer_complete_flag(&self) -> bool {
+        self.flags() & TCIF != 0
+    }
+
+    /// Get half transfer flag.
+    pub fn get_half_transfer_flag(&self) -> bool {
+        self.flags() & HTIF != 0
+    }
+
+    /// Get transfer error flag
+    pub fn get_transfer_error_flag(&self) -> bool {
+        self.flags() & TEIF != 0
+    }
+
+    /// Get fifo error flag
+    pub fn get_fifo_error_flag(&self) -> bool {
+        self.flags() & FEIF != 0
+    }
+
+    /// Get direct mode error flag
+    pub fn get_direct_mode_error_flag(&self) -> bool {
+        self.flags() & DMEIF != 0
+    }
+
+    /// Applies the channel, the direction, the addresses and `config` to the disabled stream.
+    fn configure<DIR: Direction>(
+        &mut self,
+        channel: u8,
+        direction: DIR,
+        peripheral_address: u32,
+        // pointer and len
+        buf: (u32, u16),
+        // NOTE: 0 for bytes, 1 for half words and 2 for words
+        size: u8,
+        config: config::DmaConfig,
+    ) {
+        self.disable();
+        self.clear_interrupts();
+
+        let st = self.st();
+        st.par.write(|w| unsafe { w.pa().bits(peripheral_address) });
+        st.m0ar.write(|w| unsafe { w.m0a().bits(buf.0) });
+        st.ndtr.write(|w| w.ndt().bits(buf.1));
+        st.cr.write(|w| unsafe {
+            w.chsel()
+                .bits(channel)
+                .dir()
+                .bits(direction.bits())
+                .pl()
+                .bits(config.priority.bits())
+                .msize()
+                .bits(size)
+                .psize()
+                .bits(size)
+                .minc()
+                .bit(config.memory_increment)
+                .pinc()
+                .bit(config.peripheral_increment)
+                .tcie()
+                .bit(config.transfer_complete_interrupt)
+                .htie()
+                .bit(config.half_transfer_interrupt)
+                .teie()
+                .bit(config.transfer_error_interrupt)
+                .dmeie()
+                .bit(config.direct_mode_error_interrupt)
+                .mburst()
+                .bits(config.memory_burst.bits())
+                .pburst()
+                .bits(config.peripheral_burst.bits())
+        });
+        st.fcr.write(|w| {
+            w.fth()
+                .bits(config.fifo_threshold.bits())
+                //Register is actually direct mode disable rather than fifo enable
+                .dmdis()
+                .bit(config.fifo_enable)
+                .feie()
+                .bit(config.fifo_error_interrupt)
+        });
+    }
+}
+
+/// Hands out the streams of a DMA controller at runtime.
+pub struct StreamsAllocator<DMA> {
+    // Bit n is set while stream n is free
+    free: u8,
+    _dma: PhantomData<DMA>,
+}
+
+impl<DMA: Instance> StreamsAllocator<DMA> {
+    /// Takes all the streams of the controller.
+    pub fn new(_streams: StreamsTuple<DMA>) -> Self {
+        Self {
+            free: 0xFF,
+            _dma: PhantomData,
+        }
+    }
+
+    /// Returns a free stream, if there is one.
+    pub fn alloc(&mut self) -> Option<AnyStream<DMA>> {
+        let number = (0..8).find(|n| self.free & (1 << n) != 0)?;
+        self.free &= !(1 << number);
+        Some(AnyStream::new(number))
+    }
+
+    /// Returns a free stream that serves the request of `PERIPHERAL` in direction `DIR`, together
+    /// with the channel of the request.
+    pub fn alloc_for<PERIPHERAL: 'static, DIR: Direction>(
+        &mut self,
+    ) -> Option<(AnyStream<DMA>, u8)> {
+        let (number, channel) = (0..8u8)
+            .filter(|n| self.free & (1 << n) != 0)
+            .flat_map(|n| (0..8u8).map(move |c| (n, c)))
+            .find(|&(n, c)| is_mapped::<PERIPHERAL, DIR>(DMA::NUMBER, n as usize, c))?;
+        self.free &= !(1 << number);
+        Some((AnyStream::new(number), channel))
+    }
+
+    /// Takes a stream back, the stream is disabled.
+    pub fn free(&mut self, mut stream: AnyStream<DMA>) {
+        stream.disable();
+        stream.clear_interrupts();
+        self.free |= 1 << stream.number;
+    }
+}
+
+/// A DMA transfer on a stream and a channel chosen at runtime.
+///
+/// The combination of stream, channel, direction and peripheral is checked against the request
+/// mapping when the transfer is created. Double buffering is not supported.
+pub struct AnyTransfer<DMA, PERIPHERAL, DIRECTION, BUF>
+where
+    DMA: Instance,
+{
+    stream: AnyStream<DMA>,
+    peripheral: PERIPHERAL,
+    _direction: PhantomData<DIRECTION>,
+    buf: BUF,
+}
+
+impl<DMA, PERIPHERAL, BUF> AnyTransfer<DMA, PERIPHERAL, MemoryToPeripheral, BUF>
+where
+    DMA: Instance,
+    PERIPHERAL: PeriAddress + 'static,
+    BUF: StaticReadBuffer<Word = <PERIPHERAL as PeriAddress>::MemSize>,
+{
+    /// Configures the stream for the request of the peripheral on `channel` and applies the
+    /// configuration, the resources are handed back with `InvalidRequest` if the request isn't
+    /// mapped to this stream and channel, or with `DoubleBuffering` if double buffering is
+    /// enabled in `config`.
+    pub fn init_memory_to_peripheral(
+        mut stream: AnyStream<DMA>,
+        channel: u8,
+        peripheral: PERIPHERAL,
+        buf: BUF,
+        config: config::DmaConfig,
+    ) -> Result<Self, DMAError<(AnyStream<DMA>, PERIPHERAL, BUF)>> {
+        if !is_mapped::<PERIPHERAL, MemoryToPeripheral>(
+            DMA::NUMBER,
+            stream.number as usize,
+            channel,
+        ) {
+            return Err(DMAError::InvalidRequest((stream, peripheral, buf)));
+        }
+        // Double buffering needs a second buffer
+        if config.double_buffer {
+            return Err(DMAError::DoubleBuffering((stream, peripheral, buf)));
+        }
+
+        // NOTE(unsafe) We now own this buffer and we won't call any &mut methods on it until the
+        // end of the DMA transfer
+        let (buf_ptr, buf_len) = unsafe { buf.read_buffer() };
+        stream.configure(
+            channel,
+            MemoryToPeripheral,
+            peripheral.address(),
+            (buf_ptr as u32, buf_len as u16),
+            size_bits::<PERIPHERAL>(),
+            config,
+        );
+
+        Ok(Self {
+            stream,
+            peripheral,
+            _direction: PhantomData,
+            buf,
+        })
+    }
+}
+
+impl<DMA, PERIPHERAL, BUF> AnyTransfer<DMA, PERIPHERAL, PeripheralToMemory, BUF>
+where
+    DMA: Instance,
+    PERIPHERAL: PeriAddress + 'static,
+    BUF: StaticWriteBuffer<Word = <PERIPHERAL as PeriAddress>::MemSize>,
+{
+    /// Configures the stream for the request of the peripheral on `channel` and applies the
+    /// configuration, the resources are handed back with `InvalidRequest` if the request isn't
+    /// mapped to this stream and channel, or with `DoubleBuffering` if double buffering is
+    /// enabled in `config`.
+    pub fn init_peripheral_to_memory(
+        mut stream: AnyStream<DMA>,
+        channel: u8,
+        peripheral: PERIPHERAL,
+        mut buf: BUF,
+        config: config::DmaConfig,
+    ) -> Result<Self, DMAError<(AnyStream<DMA>, PERIPHERAL, BUF)>> {
+        if !is_mapped::<PERIPHERAL, PeripheralToMemory>(
+            DMA::NUMBER,
+            stream.number as usize,
+            channel,
+        ) {
+            return Err(DMAError::InvalidRequest((stream, peripheral, buf)));
+        }
+        // Double buffering needs a second buffer
+        if config.double_buffer {
+            return Err(DMAError::DoubleBuffering((stream, peripheral, buf)));
+        }
+
+        // NOTE(unsafe) We now own this buffer and we won't call any &mut methods on it until the
+        // end of the DMA transfer
+        let (buf_ptr, buf_len) = unsafe { buf.write_buffer() };
+        stream.configure(
+            channel,
+            PeripheralToMemory,
+            peripheral.address(),
+            (buf_ptr as u32, buf_len as u16),
+            size_bits::<PERIPHERAL>(),
+            config,
+        );
+
+        Ok(Self {
+            stream,
+            peripheral,
+            _direction: PhantomData,
+            buf,
+        })
+    }
+}
+
+impl<DMA, PERIPHERAL, DIR, BUF> AnyTransfer<DMA, PERIPHERAL, DIR, BUF>
+where
+    DMA: Instance,
+{
+    /// Starts the transfer, the closure will be executed right after enabling the stream.
+    pub fn start<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        // "Preceding reads and writes cannot be moved past subsequent writes"
+        compiler_fence(Ordering::Release);
+
+        self.stream.st().cr.modify(|_, w| w.en().set_bit());
+        f(&mut self.peripheral);
+    }
+
+    /// Pauses the dma stream, the closure will be executed right before disabling the stream.
+    pub fn pause<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        f(&mut self.peripheral);
+        self.stream.disable()
+    }
+
+    /// Returns true once the transfer complete flag is set.
+    pub fn is_complete(&self) -> bool {
+        self.stream.get_transfer_complete_flag()
+    }
+
+    /// Gives access to the stream, e.g. to read its flags.
+    pub fn get_stream(&self) -> &AnyStream<DMA> {
+        &self.stream
+    }
+
+    /// Clear all interrupts for the DMA stream.
+    pub fn clear_interrupts(&mut self) {
+        self.stream.clear_interrupts();
+    }
+
+    /// Clear transfer complete interrupt (tcif) for the DMA stream.
+    pub fn clear_transfer_complete_interrupt(&mut self) {
+        self.stream.clear_transfer_complete_interrupt();
+    }
+
+    /// Stops the stream and returns the underlying resources.
+    pub fn release(mut self) -> (AnyStream<DMA>, PERIPHERAL, BUF) {
+        self.stream.disable();
+        compiler_fence(Ordering::SeqCst);
+        self.stream.clear_interrupts();
+
+        unsafe {
+            let stream = ptr::read(&self.stream);
+            let peripheral = ptr::read(&self.peripheral);
+            let buf = ptr::read(&self.buf);
+            mem::forget(self);
+            (stream, peripheral, buf)
+        }
+    }
+}
+
+impl<DMA, PERIPHERAL, DIR, BUF> Drop for AnyTransfer<DMA, PERIPHERAL, DIR, BUF>
+where
+    DMA: Instance,
+{
+    fn drop(&mut self) {
+        self.stream.disable();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Data size of the peripheral as written to the msize and psize fields.
+fn size_bits<PERIPHERAL: PeriAddress>() -> u8 {
+    (mem::size_of::<<PERIPHERAL as PeriAddress>::MemSize>() / 2) as u8
+}
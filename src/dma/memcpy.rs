@@ -0,0 +1,385 @@
+//! Memory copies and fills running on a DMA2 stream.
+
+use core::fmt::{self, Debug, Formatter};
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, AtomicU32, Ordering};
+
+use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
+
+use super::config::{BurstMode, FifoThreshold};
+use super::traits::{sealed::Sealed, Direction, Stream, StreamISR};
+use super::{MemoryToMemory, StreamX};
+use crate::pac::DMA2;
+
+/// Largest number of items of a single stream transfer, a multiple of every burst length so a
+/// long copy is split in chunks that keep their bursts.
+const MAX_ITEMS: usize = 0xFFF0;
+
+/// Bytes moved by a burst. Every burst fills the FIFO exactly once, so it never crosses a 1 KB
+/// boundary when the addresses are aligned to it.
+const BURST_BYTES: u32 = 16;
+
+/// The DMA reported a transfer error, e.g. on memory that isn't set up. Holds the resources of the
+/// copy, if any.
+pub struct TransferError<T = ()>(pub T);
+
+// Manually implement `Debug`, like `DMAError`, for buffers that don't implement it
+impl<T> Debug for TransferError<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("TransferError").finish()
+    }
+}
+
+// Source of the fills, one per DMA2 stream so it stays in place while the stream reads it.
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_PATTERN: AtomicU32 = AtomicU32::new(0);
+static FILL_PATTERNS: [AtomicU32; 8] = [NEW_PATTERN; 8];
+
+/// Element types that can be copied and filled by [`MemoryCopier`].
+pub trait Word: Sealed + Copy {
+    /// Returns the value repeated over a word.
+    #[doc(hidden)]
+    fn pattern(self) -> u32;
+}
+
+impl Sealed for u8 {}
+impl Word for u8 {
+    fn pattern(self) -> u32 {
+        u32::from_ne_bytes([self; 4])
+    }
+}
+
+impl Sealed for u16 {}
+impl Word for u16 {
+    fn pattern(self) -> u32 {
+        u32::from(self) * 0x0001_0001
+    }
+}
+
+impl Sealed for u32 {}
+impl Word for u32 {
+    fn pattern(self) -> u32 {
+        self
+    }
+}
+
+/// Copies and fills memory with a DMA2 stream, only DMA2 can do memory to memory transfers.
+///
+/// The data size, the FIFO and the bursts are chosen from the alignment of the addresses and the
+/// length: word transfers are used whenever both addresses and the length allow it and bursts of
+/// 16 bytes whenever the addresses are aligned to 16 bytes. Transfers longer than a stream can do
+/// at once are split.
+///
+/// [`copy`](Self::copy) and [`fill`](Self::fill) block until they are done, while
+/// [`start_copy`](Self::start_copy) and [`start_fill`](Self::start_fill) return a
+/// [`CopyTransfer`] driven by the transfer complete interrupt of the stream.
+pub struct MemoryCopier<const S: u8> {
+    stream: StreamX<DMA2, S>,
+}
+
+impl<const S: u8> MemoryCopier<S>
+where
+    StreamX<DMA2, S>: Stream,
+{
+    /// Uses `stream` for memory to memory transfers.
+    pub fn new(mut stream: StreamX<DMA2, S>) -> Self {
+        stream.disable();
+        stream.clear_interrupts();
+        stream.set_interrupts_enable(false, false, false, false);
+        stream.set_fifo_error_interrupt_enable(false);
+        Self { stream }
+    }
+
+    /// Returns the stream.
+    pub fn release(self) -> StreamX<DMA2, S> {
+        self.stream
+    }
+
+    /// Copies `src` into `dst` and waits for the end of the copy.
+    ///
+    /// Returns `TransferError` when the DMA reports one, e.g. when one of the slices lives in
+    /// external memory that isn't set up.
+    ///
+    /// # Panics
+    ///
+    /// * When the slices have different lengths.
+    pub fn copy<T: Word>(&mut self, src: &[T], dst: &mut [T]) -> Result<(), TransferError> {
+        assert_eq!(src.len(), dst.len(), "Slice lengths differ");
+        let mut plan = Plan::new(
+            src.as_ptr() as u32,
+            true,
+            dst.as_mut_ptr() as u32,
+            mem::size_of_val(src),
+        );
+        self.run(&mut plan)
+    }
+
+    /// Sets every element of `dst` to `value` and waits for the end of the fill.
+    ///
+    /// Returns `TransferError` when the DMA reports one, e.g. when the slice lives in external
+    /// memory that isn't set up.
+    pub fn fill<T: Word>(&mut self, dst: &mut [T], value: T) -> Result<(), TransferError> {
+        let mut plan = self.fill_plan(dst.as_mut_ptr() as u32, mem::size_of_val(dst), value);
+        self.run(&mut plan)
+    }
+
+    /// Starts copying `src` into `dst`, the copy completes in the transfer complete interrupt of
+    /// the stream.
+    ///
+    /// # Panics
+    ///
+    /// * When the buffers have different lengths.
+    pub fn start_copy<T, SRC, DST>(self, src: SRC, mut dst: DST) -> CopyTransfer<SRC, DST, S>
+    where
+        T: Word,
+        SRC: StaticReadBuffer<Word = T>,
+        DST: StaticWriteBuffer<Word = T>,
+    {
+        // NOTE(unsafe) We now own the buffers and won't touch them until the copy is over
+        let (src_ptr, src_len) = unsafe { src.read_buffer() };
+        let (dst_ptr, dst_len) = unsafe { dst.write_buffer() };
+        assert_eq!(src_len, dst_len, "Buffer lengths differ");
+
+        let plan = Plan::new(
+            src_ptr as u32,
+            true,
+            dst_ptr as u32,
+            dst_len * mem::size_of::<T>(),
+        );
+        CopyTransfer::start(self, plan, src, dst)
+    }
+
+    /// Starts setting every element of `dst` to `value`, the fill completes in the transfer
+    /// complete interrupt of the stream.
+    pub fn start_fill<T, DST>(self, mut dst: DST, value: T) -> CopyTransfer<(), DST, S>
+    where
+        T: Word,
+        DST: StaticWriteBuffer<Word = T>,
+    {
+        // NOTE(unsafe) We now own the buffer and won't touch it until the fill is over
+        let (dst_ptr, dst_len) = unsafe { dst.write_buffer() };
+        let plan = self.fill_plan(dst_ptr as u32, dst_len * mem::size_of::<T>(), value);
+        CopyTransfer::start(self, plan, (), dst)
+    }
+
+    fn fill_plan<T: Word>(&self, dst: u32, bytes: usize, value: T) -> Plan {
+        let pattern = &FILL_PATTERNS[S as usize];
+        pattern.store(value.pattern(), Ordering::Relaxed);
+        Plan::new(pattern as *const AtomicU32 as u32, false, dst, bytes)
+    }
+
+    fn run(&mut self, plan: &mut Plan) -> Result<(), TransferError> {
+        while plan.start_next(&mut self.stream) {
+            // The stream disables itself at the end of the transfer or on a transfer error
+            while StreamX::<DMA2, S>::is_enabled() {}
+            check_transfer_error::<StreamX<DMA2, S>>()?;
+        }
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads"
+        compiler_fence(Ordering::Acquire);
+        Ok(())
+    }
+}
+
+/// A copy or a fill started by [`MemoryCopier::start_copy`] or [`MemoryCopier::start_fill`].
+///
+/// [`on_interrupt`](Self::on_interrupt) has to be called from the interrupt handler of the stream
+/// to continue copies too long for a single stream transfer. Dropping the transfer stops the
+/// stream.
+pub struct CopyTransfer<SRC, DST, const S: u8>
+where
+    StreamX<DMA2, S>: Stream,
+{
+    copier: MemoryCopier<S>,
+    plan: Plan,
+    src: SRC,
+    dst: DST,
+}
+
+impl<SRC, DST, const S: u8> CopyTransfer<SRC, DST, S>
+where
+    StreamX<DMA2, S>: Stream,
+{
+    fn start(mut copier: MemoryCopier<S>, mut plan: Plan, src: SRC, dst: DST) -> Self {
+        copier.stream.set_transfer_complete_interrupt_enable(true);
+        copier.stream.set_transfer_error_interrupt_enable(true);
+        plan.start_next(&mut copier.stream);
+
+        Self {
+            copier,
+            plan,
+            src,
+            dst,
+        }
+    }
+
+    /// Handles the transfer complete interrupt of the stream and starts the next part of the copy
+    /// if there is one. Returns true once the whole copy is done.
+    ///
+    /// Returns `TransferError` when the DMA reports one, the rest of the copy is abandoned.
+    pub fn on_interrupt(&mut self) -> Result<bool, TransferError> {
+        if StreamX::<DMA2, S>::is_enabled() {
+            return Ok(false);
+        }
+        if let Err(error) = check_transfer_error::<StreamX<DMA2, S>>() {
+            self.plan.remaining = 0;
+            self.copier.stream.clear_interrupts();
+            return Err(error);
+        }
+        self.copier.stream.clear_transfer_complete_interrupt();
+        Ok(!self.plan.start_next(&mut self.copier.stream))
+    }
+
+    /// Returns true once the whole copy is done.
+    pub fn is_complete(&self) -> bool {
+        self.plan.remaining == 0 && !StreamX::<DMA2, S>::is_enabled()
+    }
+
+    /// Waits for the end of the copy, then returns the copier and the buffers.
+    ///
+    /// Returns them in a `TransferError` when the DMA reports one.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        mut self,
+    ) -> Result<(MemoryCopier<S>, SRC, DST), TransferError<(MemoryCopier<S>, SRC, DST)>> {
+        let result = loop {
+            match self.on_interrupt() {
+                Ok(true) => break Ok(()),
+                Ok(false) => {}
+                Err(error) => break Err(error),
+            }
+        };
+
+        let stream = &mut self.copier.stream;
+        stream.set_interrupts_enable(false, false, false, false);
+        stream.clear_interrupts();
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads"
+        compiler_fence(Ordering::Acquire);
+
+        unsafe {
+            let copier = ptr::read(&self.copier);
+            let src = ptr::read(&self.src);
+            let dst = ptr::read(&self.dst);
+            mem::forget(self);
+            match result {
+                Ok(()) => Ok((copier, src, dst)),
+                Err(TransferError(())) => Err(TransferError((copier, src, dst))),
+            }
+        }
+    }
+}
+
+impl<SRC, DST, const S: u8> Drop for CopyTransfer<SRC, DST, S>
+where
+    StreamX<DMA2, S>: Stream,
+{
+    fn drop(&mut self) {
+        self.copier.stream.disable();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Remaining part of a copy, moved one stream transfer at a time.
+struct Plan {
+    src: u32,
+    src_increment: bool,
+    dst: u32,
+    /// Data size of the transfers, 0 for bytes, 1 for half words and 2 for words
+    size: u8,
+    /// Remaining number of items of the data size
+    remaining: usize,
+    /// Whether the incremented addresses are aligned for bursts
+    burst_aligned: bool,
+}
+
+impl Plan {
+    fn new(src: u32, src_increment: bool, dst: u32, bytes: usize) -> Self {
+        // A fixed source always holds a whole word
+        let addresses = if src_increment { src | dst } else { dst };
+        let size = match (addresses | bytes as u32).trailing_zeros() {
+            0 => 0,
+            1 => 1,
+            _ => 2,
+        };
+
+        Self {
+            src,
+            src_increment,
+            dst,
+            size,
+            remaining: bytes >> size,
+            burst_aligned: addresses % BURST_BYTES == 0,
+        }
+    }
+
+    /// Configures and enables the stream for the next chunk, returns false if the copy is done.
+    fn start_next<STREAM: Stream>(&mut self, stream: &mut STREAM) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+
+        // Bursts only for whole bursts, a tail that is too short is moved as single transfers
+        let beats = (BURST_BYTES >> self.size) as usize;
+        let mut items = self.remaining.min(MAX_ITEMS);
+        let burst = if self.burst_aligned && items >= beats {
+            items -= items % beats;
+            match self.size {
+                0 => BurstMode::Burst16,
+                1 => BurstMode::Burst8,
+                _ => BurstMode::Burst4,
+            }
+        } else {
+            BurstMode::NoBurst
+        };
+
+        stream.disable();
+        stream.clear_interrupts();
+        stream.set_direction(MemoryToMemory::<u32>::new());
+        // In memory to memory mode the peripheral port is the source
+        stream.set_peripheral_address(self.src);
+        stream.set_memory_address(self.dst);
+        stream.set_number_of_transfers(items as u16);
+        // NOTE(unsafe) The addresses and the length are aligned to the data size
+        unsafe {
+            stream.set_peripheral_size(self.size);
+            stream.set_memory_size(self.size);
+        }
+        stream.set_peripheral_increment(self.src_increment);
+        stream.set_memory_increment(true);
+        stream.set_double_buffer(false);
+        stream.set_circular_mode(false);
+        // Memory to memory transfers always go through the FIFO
+        stream.set_fifo_enable(true);
+        stream.set_fifo_threshold(FifoThreshold::Full);
+        stream.set_memory_burst(burst);
+        stream.set_peripheral_burst(if self.src_increment {
+            burst
+        } else {
+            BurstMode::NoBurst
+        });
+
+        let bytes = (items as u32) << self.size;
+        if self.src_increment {
+            self.src += bytes;
+        }
+        self.dst += bytes;
+        self.remaining -= items;
+
+        // "Preceding reads and writes cannot be moved past subsequent writes"
+        compiler_fence(Ordering::Release);
+
+        // NOTE(unsafe) The stream is fully configured above
+        unsafe { stream.enable() };
+        true
+    }
+}
+
+fn check_transfer_error<STREAM: Stream>() -> Result<(), TransferError> {
+    if STREAM::get_transfer_error_flag() {
+        Err(TransferError(()))
+    } else {
+        Ok(())
+    }
+}
@@ -5,7 +5,17 @@
 //!
 //! This module implements Memory To Memory, Peripheral To Memory and Memory to Peripheral
 //! transfers, double buffering is supported only for Peripheral To Memory and Memory to Peripheral
-//! transfers.
+//! transfers. [CircBuffer](struct.CircBuffer.html) keeps a Peripheral To Memory stream running in
+//! circular mode and lets software drain the data at its own pace.
+//! [MemoryCopier](struct.MemoryCopier.html) copies and fills memory on a DMA2 stream without
+//! setting up a full transfer.
+//!
+//! When the stream has to be chosen at runtime, a [StreamsAllocator](struct.StreamsAllocator.html)
+//! hands out [AnyStream](struct.AnyStream.html)s and [AnyTransfer](struct.AnyTransfer.html)
+//! checks the peripheral-stream-channel-direction combination when it is created.
+//!
+//! With the `async` feature, transfers can be awaited, see
+//! [Transfer::wait_transfer_complete](struct.Transfer.html#method.wait_transfer_complete).
 
 use core::{
     fmt::{self, Debug, Formatter},
@@ -20,6 +30,16 @@ use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
 use crate::pac::RCC;
 use crate::{pac, rcc};
 
+mod circular;
+pub use circular::CircBuffer;
+mod erased;
+pub use erased::{AnyStream, AnyTransfer, StreamsAllocator};
+mod memcpy;
+pub use memcpy::{CopyTransfer, MemoryCopier, TransferError, Word};
+#[cfg(feature = "async")]
+mod wait;
+#[cfg(feature = "async")]
+pub use wait::TransferEvent;
 pub mod traits;
 use traits::{
     sealed::{Bits, Sealed},
@@ -35,6 +55,10 @@ pub enum DMAError<T> {
     SmallBuffer(T),
     /// Overrun during a double buffering or circular transfer.
     Overrun(T),
+    /// The stream and channel chosen at runtime don't serve the request of the peripheral.
+    InvalidRequest(T),
+    /// Double buffering was requested from a transfer that only takes one buffer.
+    DoubleBuffering(T),
 }
 
 // Manually implement `Debug`, so we can have debug information even with a buffer `T` that doesn't
@@ -46,10 +70,34 @@ impl<T> Debug for DMAError<T> {
             DMAError::NotReady(_) => f.debug_tuple("NotReady").finish(),
             DMAError::SmallBuffer(_) => f.debug_tuple("SmallBuffer").finish(),
             DMAError::Overrun(_) => f.debug_tuple("Overrun").finish(),
+            DMAError::InvalidRequest(_) => f.debug_tuple("InvalidRequest").finish(),
+            DMAError::DoubleBuffering(_) => f.debug_tuple("DoubleBuffering").finish(),
         }
     }
 }
 
+/// Error flag of a DMA stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamErrorKind {
+    /// Bus error, the stream was disabled by the hardware.
+    Transfer,
+    /// FIFO overrun or underrun, or a FIFO configuration not supported by the data sizes.
+    Fifo,
+    /// A request came before the previous data was transferred in direct mode.
+    DirectMode,
+}
+
+/// Error reported by [`Transfer::check_errors`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamError {
+    /// The flag that was set.
+    pub kind: StreamErrorKind,
+    /// Number of transfers left (NDTR) when the error was checked.
+    pub remaining: u16,
+    /// Whether the stream was restarted by the error recovery.
+    pub restarted: bool,
+}
+
 /// Possible DMA's directions.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DmaDirection {
@@ -212,26 +260,13 @@ impl<DMA, const S: u8> StreamX<DMA, S> {
 }
 
 impl<DMA: Instance, const S: u8> StreamX<DMA, S> {
-    #[cfg(not(any(
-        feature = "stm32f411",
-        feature = "stm32f413",
-        feature = "stm32f423",
-        feature = "stm32f410"
-    )))]
+    /// Number of the DMA controller of the stream.
+    pub(crate) const DMA_NUMBER: usize = DMA::NUMBER;
+
     #[inline(always)]
     unsafe fn st() -> &'static pac::dma2::ST {
         &(*DMA::ptr()).st[S as usize]
     }
-    #[cfg(any(
-        feature = "stm32f411",
-        feature = "stm32f413",
-        feature = "stm32f423",
-        feature = "stm32f410"
-    ))]
-    #[inline(always)]
-    unsafe fn st() -> &'static pac::dma1::ST {
-        &(*DMA::ptr()).st[S as usize]
-    }
 }
 
 /// Stream 0 on the DMA controller.
@@ -485,6 +520,13 @@ where
             .modify(|_, w| w.dbm().bit(double_buffer));
     }
 
+    #[inline(always)]
+    fn set_circular_mode(&mut self, circular: bool) {
+        unsafe { Self::st() }
+            .cr
+            .modify(|_, w| w.circ().bit(circular));
+    }
+
     #[inline(always)]
     fn set_fifo_threshold(&mut self, fifo_threshold: config::FifoThreshold) {
         unsafe { Self::st() }
@@ -666,9 +708,6 @@ dma_channel!(
     (Channel7, 7),
 );
 
-#[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
-dma_channel!((Channel8, 8), (Channel9, 9),);
-
 /// Contains types related to DMA configuration.
 pub mod config {
     use super::Bits;
@@ -763,6 +802,7 @@ pub mod config {
         pub(crate) fifo_enable: bool,
         pub(crate) memory_burst: BurstMode,
         pub(crate) peripheral_burst: BurstMode,
+        pub(crate) error_recovery: bool,
     }
 
     impl Default for DmaConfig {
@@ -781,6 +821,7 @@ pub mod config {
                 fifo_enable: false,
                 memory_burst: BurstMode::NoBurst,
                 peripheral_burst: BurstMode::NoBurst,
+                error_recovery: false,
             }
         }
     }
@@ -865,6 +906,13 @@ pub mod config {
             self.peripheral_burst = peripheral_burst;
             self
         }
+        /// Set the error_recovery. When enabled, `Transfer::check_errors` restarts the stream
+        /// with the same buffers after a transfer or fifo error.
+        #[inline(always)]
+        pub fn error_recovery(mut self, error_recovery: bool) -> Self {
+            self.error_recovery = error_recovery;
+            self
+        }
     }
 }
 
@@ -879,8 +927,11 @@ where
     _direction: PhantomData<DIRECTION>,
     buf: Option<BUF>,
     double_buf: Option<BUF>,
-    // Used when double buffering
+    // Number of transfers programmed in NDTR: the length of the current buffer, or the shorter
+    // of the two buffers when double buffering, as NDTR counts a single buffer
     transfer_length: u16,
+    // Restart the stream after a transfer or fifo error
+    error_recovery: bool,
 }
 
 impl<STREAM, PERIPHERAL, BUF, const CHANNEL: u8>
@@ -925,6 +976,7 @@ where
             buf: Some(buf),
             double_buf,
             transfer_length: n_transfers,
+            error_recovery: config.error_recovery,
         }
     }
 
@@ -1056,6 +1108,7 @@ where
             buf: Some(buf),
             double_buf,
             transfer_length: n_transfers,
+            error_recovery: config.error_recovery,
         }
     }
 
@@ -1193,6 +1246,7 @@ where
             buf: Some(buf),
             double_buf: Some(double_buf),
             transfer_length: n_transfers,
+            error_recovery: config.error_recovery,
         }
     }
 
@@ -1330,6 +1384,55 @@ where
         self.stream.clear_fifo_error_interrupt();
     }
 
+    /// Checks the error flags of the stream, clearing them.
+    ///
+    /// The error reports which flag was set, the transfer error first, and the number of
+    /// transfers that were left. With error recovery enabled in `DmaConfig`, the stream is reset
+    /// and restarted with the same buffers after a transfer or fifo error, a direct mode error
+    /// doesn't stop the stream and is only reported.
+    pub fn check_errors(&mut self) -> Result<(), StreamError> {
+        let kind = if STREAM::get_transfer_error_flag() {
+            StreamErrorKind::Transfer
+        } else if STREAM::get_fifo_error_flag() {
+            StreamErrorKind::Fifo
+        } else if STREAM::get_direct_mode_error_flag() {
+            StreamErrorKind::DirectMode
+        } else {
+            return Ok(());
+        };
+        let remaining = STREAM::get_number_of_transfers();
+
+        let restarted = self.error_recovery && kind != StreamErrorKind::DirectMode;
+        if restarted {
+            self.restart();
+        } else {
+            self.stream.clear_transfer_error_interrupt();
+            self.stream.clear_fifo_error_interrupt();
+            self.stream.clear_direct_mode_error_interrupt();
+        }
+
+        Err(StreamError {
+            kind,
+            remaining,
+            restarted,
+        })
+    }
+
+    /// Restarts the stream from the start of the current buffer.
+    fn restart(&mut self) {
+        self.stream.disable();
+        self.stream.clear_interrupts();
+        self.stream.set_number_of_transfers(self.transfer_length);
+
+        // "Preceding reads and writes cannot be moved past subsequent writes"
+        compiler_fence(Ordering::Release);
+
+        // NOTE(unsafe) The stream keeps the configuration and the buffers of the transfer
+        unsafe {
+            self.stream.enable();
+        }
+    }
+
     /// Get the underlying stream of the transfer.
     ///
     /// # Safety
@@ -1493,6 +1596,7 @@ where
         let (buf_ptr, buf_len) = ptr_and_len;
         self.stream.set_memory_address(buf_ptr as u32);
         self.stream.set_number_of_transfers(buf_len as u16);
+        self.transfer_length = buf_len;
         let old_buf = self.buf.replace(new_buf);
 
         unsafe {
@@ -1569,6 +1673,7 @@ where
         let (buf_ptr, buf_len) = ptr_and_len;
         self.stream.set_memory_address(buf_ptr as u32);
         self.stream.set_number_of_transfers(buf_len as u16);
+        self.transfer_length = buf_len;
         self.buf.replace(new_buf);
 
         self.stream.enable();
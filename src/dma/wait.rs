@@ -0,0 +1,205 @@
+//! Asynchronous waiting on DMA transfers
+//!
+//! This module is only available if the `async` feature is enabled.
+//!
+//! When a transfer waits, the transfer complete and transfer error interrupts of its stream are
+//! enabled, and the half transfer interrupt too for [`Transfer::wait_event`]. The stream's
+//! interrupt handler then disables them again and wakes the task waiting on the stream, leaving
+//! the flags to the future. The interrupts of the stream have to be unmasked in the NVIC and
+//! should not be enabled through [`DmaConfig`](super::config::DmaConfig) at the same time.
+//!
+//! With the `dma-handlers` feature the HAL defines the `DMA1_STREAM0` to `DMA2_STREAM7` interrupt
+//! handlers. Otherwise [`StreamX::on_interrupt`] has to be called from them.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use atomic_waker::AtomicWaker;
+
+use super::traits::{Channel, DMASet, Direction, Instance, PeriAddress, Stream, StreamISR};
+use super::{ChannelX, StreamX, Transfer};
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_WAKER: AtomicWaker = AtomicWaker::new();
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_DMA_WAKERS: [AtomicWaker; 8] = [NEW_WAKER; 8];
+static WAKERS: [[AtomicWaker; 8]; 2] = [NEW_DMA_WAKERS; 2];
+
+/// Event that ended a wait on a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferEvent {
+    /// Half of the current buffer was transferred, the flag was cleared
+    HalfTransfer,
+    /// The current buffer was transferred. In double buffer mode, the flag is left for
+    /// `next_transfer`.
+    TransferComplete,
+    /// A bus error stopped the stream, the flag is left set for
+    /// [`check_errors`](Transfer::check_errors)
+    TransferError,
+}
+
+impl<DMA: Instance, const S: u8> StreamX<DMA, S>
+where
+    Self: Stream,
+{
+    /// Handles the interrupt of the stream and wakes the task waiting on it
+    ///
+    /// Must be called from the stream's interrupt handler unless the `dma-handlers` feature is
+    /// enabled.
+    pub fn on_interrupt() {
+        cortex_m::interrupt::free(|_| {
+            // NOTE(unsafe) the interrupt enables are only modified inside critical sections
+            // while a transfer is awaited
+            unsafe { Self::st() }.cr.modify(|_, w| {
+                w.tcie()
+                    .clear_bit()
+                    .htie()
+                    .clear_bit()
+                    .teie()
+                    .clear_bit()
+                    .dmeie()
+                    .clear_bit()
+            });
+        });
+        Self::waker().wake();
+    }
+
+    fn waker() -> &'static AtomicWaker {
+        &WAKERS[DMA::NUMBER - 1][S as usize]
+    }
+}
+
+/// Future that resolves on the next event of a stream, dropping it before stops the stream
+struct StreamFuture<'a, DMA: Instance, const S: u8>
+where
+    StreamX<DMA, S>: Stream,
+{
+    stream: &'a mut StreamX<DMA, S>,
+    half_transfer: bool,
+    done: bool,
+}
+
+impl<DMA: Instance, const S: u8> Future for StreamFuture<'_, DMA, S>
+where
+    StreamX<DMA, S>: Stream,
+{
+    type Output = TransferEvent;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<TransferEvent> {
+        StreamX::<DMA, S>::waker().register(cx.waker());
+
+        let event = if StreamX::<DMA, S>::get_transfer_error_flag() {
+            Some(TransferEvent::TransferError)
+        } else if StreamX::<DMA, S>::get_transfer_complete_flag() {
+            Some(TransferEvent::TransferComplete)
+        } else if self.half_transfer && StreamX::<DMA, S>::get_half_transfer_flag() {
+            self.stream.clear_half_transfer_interrupt();
+            Some(TransferEvent::HalfTransfer)
+        } else {
+            None
+        };
+
+        if let Some(event) = event {
+            self.done = true;
+            return Poll::Ready(event);
+        }
+
+        // A flag raised after the checks above triggers the interrupt as soon as it is enabled
+        let half_transfer = self.half_transfer;
+        cortex_m::interrupt::free(|_| {
+            self.stream.set_transfer_complete_interrupt_enable(true);
+            self.stream.set_transfer_error_interrupt_enable(true);
+            if half_transfer {
+                self.stream.set_half_transfer_interrupt_enable(true);
+            }
+        });
+        Poll::Pending
+    }
+}
+
+impl<DMA: Instance, const S: u8> Drop for StreamFuture<'_, DMA, S>
+where
+    StreamX<DMA, S>: Stream,
+{
+    fn drop(&mut self) {
+        if !self.done {
+            // Cancelled, the buffers can't be handed out while the stream still uses them
+            self.stream.disable();
+        }
+    }
+}
+
+impl<DMA, PERIPHERAL, DIR, BUF, const S: u8, const CHANNEL: u8>
+    Transfer<StreamX<DMA, S>, PERIPHERAL, DIR, BUF, CHANNEL>
+where
+    DMA: Instance,
+    StreamX<DMA, S>: Stream,
+    ChannelX<CHANNEL>: Channel,
+    DIR: Direction,
+    PERIPHERAL: PeriAddress + DMASet<StreamX<DMA, S>, DIR, CHANNEL>,
+{
+    /// Waits until the transfer complete flag is set, i.e. until the end of the transfer or, in
+    /// double buffer mode, of the current buffer
+    ///
+    /// Returns `TransferError` instead when a bus error stopped the stream. Dropping the future
+    /// before it completes disables the stream.
+    pub async fn wait_transfer_complete(&mut self) -> TransferEvent {
+        StreamFuture {
+            stream: &mut self.stream,
+            half_transfer: false,
+            done: false,
+        }
+        .await
+    }
+
+    /// Waits for the next half transfer, transfer complete or transfer error event
+    ///
+    /// Meant for double buffering, where the half transfer event tells that the first half of
+    /// the current buffer can be processed. The half transfer flag is cleared when it is
+    /// returned. Dropping the future before it completes disables the stream.
+    pub async fn wait_event(&mut self) -> TransferEvent {
+        StreamFuture {
+            stream: &mut self.stream,
+            half_transfer: true,
+            done: false,
+        }
+        .await
+    }
+}
+
+#[cfg(feature = "dma-handlers")]
+mod handlers {
+    use super::super::StreamX;
+    use crate::pac::{interrupt, DMA1, DMA2};
+
+    macro_rules! stream_handlers {
+        ($($name:ident: $dma:ty, $s:literal;)+) => {
+            $(
+                #[interrupt]
+                fn $name() {
+                    StreamX::<$dma, $s>::on_interrupt();
+                }
+            )+
+        };
+    }
+
+    stream_handlers!(
+        DMA1_STREAM0: DMA1, 0;
+        DMA1_STREAM1: DMA1, 1;
+        DMA1_STREAM2: DMA1, 2;
+        DMA1_STREAM3: DMA1, 3;
+        DMA1_STREAM4: DMA1, 4;
+        DMA1_STREAM5: DMA1, 5;
+        DMA1_STREAM6: DMA1, 6;
+        DMA1_STREAM7: DMA1, 7;
+        DMA2_STREAM0: DMA2, 0;
+        DMA2_STREAM1: DMA2, 1;
+        DMA2_STREAM2: DMA2, 2;
+        DMA2_STREAM3: DMA2, 3;
+        DMA2_STREAM4: DMA2, 4;
+        DMA2_STREAM5: DMA2, 5;
+        DMA2_STREAM6: DMA2, 6;
+        DMA2_STREAM7: DMA2, 7;
+    );
+}
@@ -10,6 +10,10 @@
 //! ```
 //!
 //! This gives you a struct containing all the pins `px0..px15`.
+//!
+//! If one of the package features (`lqfp64`, `lqfp100`, `lqfp144` or `lqfp176`) is enabled, only
+//! the pins bonded out on that package are available, both in `Parts` and as pin types. Without a
+//! package feature every pin of every enabled port is exposed.
 //! By default pins are in floating input mode. You can change their modes.
 //! For example, to set `pa5` high, you would call
 //!
@@ -56,11 +60,13 @@ use crate::pac::EXTI;
 use crate::syscfg::SysCfg;
 
 mod convert;
-pub(crate) use convert::{Const, SetAlternate, SetAlternateOD};
+pub(crate) use convert::{Const, PinMode, SetAlternate, SetAlternateOD};
 mod partially_erased;
 pub use partially_erased::{PEPin, PartiallyErasedPin};
 mod erased;
 pub use erased::{EPin, ErasedPin};
+#[cfg(feature = "async")]
+pub mod wait;
 
 /// A filler pin type
 pub struct NoPin;
@@ -583,12 +589,13 @@ impl<const P: char, const N: u8> IoPin<Self, Pin<Output<PushPull>, P, N>>
 
 macro_rules! gpio {
     ($GPIOX:ident, $gpiox:ident, $PEPin:ident, $port_id:expr, $PXn:ident, [
-        $($PXi:ident: ($pxi:ident, $i:expr, $MODE:ty),)+
+        $($(#[$attr:meta])* $PXi:ident: ($pxi:ident, $i:expr, $MODE:ty),)+
     ]) => {
         /// GPIO
         pub mod $gpiox {
             use crate::pac::{$GPIOX, RCC};
             use crate::rcc::{Enable, Reset};
+            #[allow(unused_imports)]
             use super::{
                 Floating, Input,
             };
@@ -597,6 +604,7 @@ macro_rules! gpio {
             pub struct Parts {
                 $(
                     /// Pin
+                    $(#[$attr])*
                     pub $pxi: $PXi<$MODE>,
                 )+
             }
@@ -615,6 +623,7 @@ macro_rules! gpio {
                     }
                     Parts {
                         $(
+                            $(#[$attr])*
                             $pxi: $PXi::new(),
                         )+
                     }
@@ -624,6 +633,7 @@ macro_rules! gpio {
             pub type $PXn<MODE> = super::PEPin<MODE, $port_id>;
 
             $(
+                $(#[$attr])*
                 pub type $PXi<MODE> = super::Pin<MODE, $port_id, $i>;
             )+
 
@@ -690,81 +700,144 @@ gpio!(GPIOC, gpioc, PC, 'C', PCn, [
 
 #[cfg(feature = "gpiod")]
 gpio!(GPIOD, gpiod, PD, 'D', PDn, [
+    #[cfg(not(feature = "lqfp64"))]
     PD0: (pd0, 0, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD1: (pd1, 1, Input<Floating>),
     PD2: (pd2, 2, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD3: (pd3, 3, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD4: (pd4, 4, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD5: (pd5, 5, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD6: (pd6, 6, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD7: (pd7, 7, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD8: (pd8, 8, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD9: (pd9, 9, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD10: (pd10, 10, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD11: (pd11, 11, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD12: (pd12, 12, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD13: (pd13, 13, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD14: (pd14, 14, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PD15: (pd15, 15, Input<Floating>),
 ]);
 
 #[cfg(feature = "gpioe")]
 gpio!(GPIOE, gpioe, PE, 'E', PEn, [
+    #[cfg(not(feature = "lqfp64"))]
     PE0: (pe0, 0, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE1: (pe1, 1, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE2: (pe2, 2, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE3: (pe3, 3, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE4: (pe4, 4, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE5: (pe5, 5, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE6: (pe6, 6, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE7: (pe7, 7, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE8: (pe8, 8, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE9: (pe9, 9, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE10: (pe10, 10, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE11: (pe11, 11, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE12: (pe12, 12, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE13: (pe13, 13, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE14: (pe14, 14, Input<Floating>),
+    #[cfg(not(feature = "lqfp64"))]
     PE15: (pe15, 15, Input<Floating>),
 ]);
 
 #[cfg(feature = "gpiof")]
 gpio!(GPIOF, gpiof, PF, 'F', PFn, [
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF0: (pf0, 0, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF1: (pf1, 1, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF2: (pf2, 2, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF3: (pf3, 3, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF4: (pf4, 4, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF5: (pf5, 5, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF6: (pf6, 6, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF7: (pf7, 7, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF8: (pf8, 8, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF9: (pf9, 9, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF10: (pf10, 10, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF11: (pf11, 11, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF12: (pf12, 12, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF13: (pf13, 13, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF14: (pf14, 14, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PF15: (pf15, 15, Input<Floating>),
 ]);
 
 #[cfg(feature = "gpiog")]
 gpio!(GPIOG, gpiog, PG, 'G', PGn, [
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG0: (pg0, 0, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG1: (pg1, 1, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG2: (pg2, 2, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG3: (pg3, 3, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG4: (pg4, 4, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG5: (pg5, 5, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG6: (pg6, 6, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG7: (pg7, 7, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG8: (pg8, 8, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG9: (pg9, 9, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG10: (pg10, 10, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG11: (pg11, 11, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG12: (pg12, 12, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG13: (pg13, 13, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG14: (pg14, 14, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
     PG15: (pg15, 15, Input<Floating>),
 ]);
 
@@ -772,39 +845,89 @@ gpio!(GPIOG, gpiog, PG, 'G', PGn, [
 gpio!(GPIOH, gpioh, PH, 'H', PHn, [
     PH0: (ph0, 0, Input<Floating>),
     PH1: (ph1, 1, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH2: (ph2, 2, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH3: (ph3, 3, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH4: (ph4, 4, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH5: (ph5, 5, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH6: (ph6, 6, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH7: (ph7, 7, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH8: (ph8, 8, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH9: (ph9, 9, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH10: (ph10, 10, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH11: (ph11, 11, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH12: (ph12, 12, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH13: (ph13, 13, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH14: (ph14, 14, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PH15: (ph15, 15, Input<Floating>),
 ]);
 
 #[cfg(feature = "gpioi")]
 gpio!(GPIOI, gpioi, PI, 'I', PIn, [
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI0: (pi0, 0, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI1: (pi1, 1, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI2: (pi2, 2, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI3: (pi3, 3, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI4: (pi4, 4, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI5: (pi5, 5, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI6: (pi6, 6, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI7: (pi7, 7, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI8: (pi8, 8, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI9: (pi9, 9, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI10: (pi10, 10, Input<Floating>),
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
     PI11: (pi11, 11, Input<Floating>),
+    #[cfg(not(any(
+        feature = "lqfp64",
+        feature = "lqfp100",
+        feature = "lqfp144",
+        feature = "lqfp176"
+    )))]
     PI12: (pi12, 12, Input<Floating>),
+    #[cfg(not(any(
+        feature = "lqfp64",
+        feature = "lqfp100",
+        feature = "lqfp144",
+        feature = "lqfp176"
+    )))]
     PI13: (pi13, 13, Input<Floating>),
+    #[cfg(not(any(
+        feature = "lqfp64",
+        feature = "lqfp100",
+        feature = "lqfp144",
+        feature = "lqfp176"
+    )))]
     PI14: (pi14, 14, Input<Floating>),
+    #[cfg(not(any(
+        feature = "lqfp64",
+        feature = "lqfp100",
+        feature = "lqfp144",
+        feature = "lqfp176"
+    )))]
     PI15: (pi15, 15, Input<Floating>),
 ]);
 
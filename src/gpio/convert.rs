@@ -575,6 +575,35 @@ where
         self._set_state(state);
         self.with_mode(f)
     }
+
+    /// Temporarily configures this pin as a push-pull output in `state`, then as alternate
+    /// function `A`.
+    ///
+    /// Unlike [`with_push_pull_output_in_state`](Pin::with_push_pull_output_in_state), the pin
+    /// goes straight from the output to the alternate function, without passing through `MODE`,
+    /// so that it keeps driving the line. Only the pull of `MODE` is restored.
+    pub(crate) fn with_push_pull_output_then_alternate<R, F, const A: u8>(
+        &mut self,
+        state: PinState,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(&mut Pin<Output<PushPull>, P, N>) -> R,
+    {
+        self._set_state(state);
+        self.mode::<Output<PushPull>>();
+
+        let result = f(&mut Pin::new());
+
+        self.set_alternate::<A>();
+        let offset = 2 * N;
+        unsafe {
+            (*Gpio::<P>::ptr())
+                .pupdr
+                .modify(|r, w| w.bits((r.bits() & !(0b11 << offset)) | (MODE::PUPDR << offset)));
+        }
+        result
+    }
 }
 
 struct ResetMode<'a, ORIG: PinMode, const P: char, const N: u8> {
@@ -0,0 +1,206 @@
+//! Asynchronous waiting on input pins using the EXTI controller
+//!
+//! This module is only available if the `async` feature is enabled.
+//!
+//! Before awaiting on a pin, its EXTI line has to be routed to the pin's port with
+//! [`ExtiPin::make_interrupt_source`] and the EXTI interrupts have to be unmasked in the NVIC.
+//! When one of the futures is polled, it configures the trigger edge and unmasks the EXTI line.
+//! The interrupt handler then masks the line again, clears its pending bit and wakes the task
+//! waiting on it, so every line has at most one waiter at a time.
+//!
+//! With the `exti-handlers` feature the HAL defines the `EXTI0` to `EXTI4`, `EXTI9_5` and
+//! `EXTI15_10` interrupt handlers. Otherwise [`on_interrupt`] has to be called from them.
+
+use core::convert::Infallible;
+use core::future::Future;
+use core::task::{Context, Poll};
+
+use atomic_waker::AtomicWaker;
+
+use super::{Edge, ExtiPin, Input, Pin};
+use crate::pac::EXTI;
+
+/// Number of EXTI lines that can be connected to GPIO pins
+const LINES: usize = 16;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_WAKER: AtomicWaker = AtomicWaker::new();
+static WAKERS: [AtomicWaker; LINES] = [NEW_WAKER; LINES];
+
+/// Handles the pending GPIO EXTI lines and wakes the tasks waiting on them
+///
+/// Must be called from the `EXTI0` to `EXTI4`, `EXTI9_5` and `EXTI15_10` interrupt handlers
+/// unless the `exti-handlers` feature is enabled.
+pub fn on_interrupt() {
+    let pending = cortex_m::interrupt::free(|_| {
+        // NOTE(unsafe) the registers are only modified inside critical sections
+        let exti = unsafe { &*EXTI::ptr() };
+        let pending = exti.pr.read().bits() & exti.imr.read().bits() & ((1 << LINES) - 1);
+        // Masking the line tells the future that its edge has arrived
+        exti.imr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !pending) });
+        exti.pr.write(|w| unsafe { w.bits(pending) });
+        pending
+    });
+
+    for (line, waker) in WAKERS.iter().enumerate() {
+        if pending & (1 << line) != 0 {
+            waker.wake();
+        }
+    }
+}
+
+/// Future that resolves once the EXTI line of a pin has triggered
+struct ExtiFuture<'a, MODE, const P: char, const N: u8> {
+    pin: &'a mut Pin<Input<MODE>, P, N>,
+}
+
+impl<'a, MODE, const P: char, const N: u8> ExtiFuture<'a, MODE, P, N> {
+    fn new(pin: &'a mut Pin<Input<MODE>, P, N>, edge: Edge) -> Self {
+        cortex_m::interrupt::free(|_| {
+            // NOTE(unsafe) the EXTI registers are only modified inside critical sections,
+            // so the stolen instance can't race with another owner
+            let mut exti = unsafe { crate::pac::Peripherals::steal().EXTI };
+            pin.trigger_on_edge(&mut exti, edge);
+            pin.clear_interrupt_pending_bit();
+            pin.enable_interrupt(&mut exti);
+        });
+        Self { pin }
+    }
+}
+
+impl<MODE, const P: char, const N: u8> Future for ExtiFuture<'_, MODE, P, N> {
+    type Output = ();
+
+    fn poll(self: core::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        WAKERS[N as usize].register(cx.waker());
+
+        // The interrupt handler masks the line once the edge was seen
+        let imr = unsafe { (*EXTI::ptr()).imr.read().bits() };
+        if imr & (1 << N) == 0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<MODE, const P: char, const N: u8> Drop for ExtiFuture<'_, MODE, P, N> {
+    fn drop(&mut self) {
+        cortex_m::interrupt::free(|_| {
+            let mut exti = unsafe { crate::pac::Peripherals::steal().EXTI };
+            self.pin.disable_interrupt(&mut exti);
+        });
+    }
+}
+
+impl<MODE, const P: char, const N: u8> Pin<Input<MODE>, P, N> {
+    /// Waits for a rising edge on the pin
+    pub async fn wait_for_rising_edge(&mut self) {
+        ExtiFuture::new(self, Edge::Rising).await
+    }
+
+    /// Waits for a falling edge on the pin
+    pub async fn wait_for_falling_edge(&mut self) {
+        ExtiFuture::new(self, Edge::Falling).await
+    }
+
+    /// Waits for either a rising or a falling edge on the pin
+    pub async fn wait_for_any_edge(&mut self) {
+        ExtiFuture::new(self, Edge::RisingFalling).await
+    }
+
+    /// Waits until the pin is high, returning immediately if it already is
+    pub async fn wait_for_high(&mut self) {
+        // Arm the line before sampling so an edge right after the check isn't lost
+        let fut = ExtiFuture::new(self, Edge::Rising);
+        if fut.pin.is_high() {
+            return;
+        }
+        fut.await
+    }
+
+    /// Waits until the pin is low, returning immediately if it already is
+    pub async fn wait_for_low(&mut self) {
+        let fut = ExtiFuture::new(self, Edge::Falling);
+        if fut.pin.is_low() {
+            return;
+        }
+        fut.await
+    }
+}
+
+impl<MODE, const P: char, const N: u8> embedded_hal_one::digital::ErrorType
+    for Pin<Input<MODE>, P, N>
+{
+    type Error = Infallible;
+}
+
+impl<MODE, const P: char, const N: u8> embedded_hal_async::digital::Wait
+    for Pin<Input<MODE>, P, N>
+{
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        Pin::wait_for_high(self).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        Pin::wait_for_low(self).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        Pin::wait_for_rising_edge(self).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        Pin::wait_for_falling_edge(self).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        Pin::wait_for_any_edge(self).await;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "exti-handlers")]
+mod handlers {
+    use crate::pac::interrupt;
+
+    #[interrupt]
+    fn EXTI0() {
+        super::on_interrupt();
+    }
+
+    #[interrupt]
+    fn EXTI1() {
+        super::on_interrupt();
+    }
+
+    #[interrupt]
+    fn EXTI2() {
+        super::on_interrupt();
+    }
+
+    #[interrupt]
+    fn EXTI3() {
+        super::on_interrupt();
+    }
+
+    #[interrupt]
+    fn EXTI4() {
+        super::on_interrupt();
+    }
+
+    #[interrupt]
+    fn EXTI9_5() {
+        super::on_interrupt();
+    }
+
+    #[interrupt]
+    fn EXTI15_10() {
+        super::on_interrupt();
+    }
+}
@@ -104,14 +104,20 @@ pin!(PinSda<I2C1> for gpiob::PB9<4>);
 
 pin!(PinScl<I2C2> for gpiob::PB10<4>);
 pin!(PinSda<I2C2> for gpiob::PB11<4>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
 pin!(PinScl<I2C2> for gpiof::PF1<4>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
 pin!(PinSda<I2C2> for gpiof::PF0<4>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
 pin!(PinScl<I2C2> for gpioh::PH4<4>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
 pin!(PinSda<I2C2> for gpioh::PH5<4>);
 
 pin!(PinScl<I2C3> for gpioa::PA8<4>);
 pin!(PinSda<I2C3> for gpioc::PC9<4>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
 pin!(PinScl<I2C3> for gpioh::PH7<4>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
 pin!(PinSda<I2C3> for gpioh::PH8<4>);
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
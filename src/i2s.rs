@@ -115,7 +115,6 @@ mod ws_pins {
     mod common {
         use crate::gpio::gpioa::{PA15, PA4};
         use crate::gpio::gpiob::{PB12, PB9};
-        use crate::gpio::gpioi::PI0;
         use crate::pac::{SPI2, SPI3};
         // All STM32F2 models support these pins
         pin_ws! {
@@ -123,10 +122,15 @@ mod ws_pins {
            SPI2 => PB12<5>,
            SPI3 => PA4<6>,
            SPI3 => PA15<6>,
-           SPI2 => PI0<5>,
         }
+    }
+
+    // GPIOI is only bonded out on 176 pin packages
+    #[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
+    mod pi0 {
         use crate::gpio::gpioi::PI0;
         use crate::pac::SPI2;
+        pin_ws! { SPI2 => PI0<5>, }
     }
 }
 
@@ -10,6 +10,25 @@ compile_error!(
         stm32f217"
 );
 
+#[cfg(any(
+    all(
+        feature = "lqfp64",
+        any(feature = "lqfp100", feature = "lqfp144", feature = "lqfp176")
+    ),
+    all(
+        feature = "lqfp100",
+        any(feature = "lqfp144", feature = "lqfp176")
+    ),
+    all(feature = "lqfp144", feature = "lqfp176"),
+))]
+compile_error!(
+    "Only one of the following package features can be enabled:
+        lqfp64
+        lqfp100
+        lqfp144
+        lqfp176"
+);
+
 #[cfg(feature = "device-selected")]
 pub use embedded_hal as hal;
 
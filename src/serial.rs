@@ -15,6 +15,66 @@
 //! the embedded-hal read and write traits with `u16` as the word type. You can use these
 //! implementations for 9-bit words.
 //!
+//! # embedded-io
+//!
+//! `Serial`, `Rx` and `Tx` with 8 bit words implement the `embedded-io` traits. With the `async`
+//! feature they also implement the `embedded-io-async` traits, waiting on the UART/USART interrupt
+//! whose handler has to call [`on_interrupt`] unless the `usart-handlers` feature is enabled.
+//!
+//! # Breaks
+//!
+//! [`Tx::send_break`] sends a break of one frame, received as [`Error::Break`]. DMX512 needs a
+//! longer break, sent by [`Serial::send_extended_break`] with the TX pin driven as a GPIO.
+//!
+//! # Buffered communication
+//!
+//! [`BufferedSerial`] queues the received and transmitted bytes, moved from and to the USART by
+//! its interrupt handler.
+//!
+//! # DMA reception
+//!
+//! [`DmaRx`] receives variable length frames with DMA, using idle line detection to find the end
+//! of each frame.
+//!
+//! # Flow control
+//!
+//! The USARTs support RTS/CTS hardware flow control, with a `Serial` built by
+//! [`Serial::with_flow_control`] from `(TX, RX, RTS, CTS)` pins.
+//!
+//! # Half duplex
+//!
+//! [`Serial::half_duplex`] communicates on the TX pin alone, in open drain mode, as used by smart
+//! servos and 1-Wire adapters.
+//!
+//! # LIN
+//!
+//! LIN mode is enabled with [`Config::lin`](config::Config::lin). The [`lin`] module adds the
+//! frame layer on top: break, sync and protected identifier headers, and frame checksums.
+//!
+//! # Modbus RTU
+//!
+//! The [`modbus`] module frames Modbus RTU requests and responses, measuring the silent intervals
+//! between them with a timer, for masters and slaves.
+//!
+//! # IrDA
+//!
+//! [`Serial::irda`] sets up the USART as an IrDA SIR encoder and decoder, in normal or low-power
+//! mode.
+//!
+//! # RS-485
+//!
+//! [`Rs485`] drives the driver enable input of an RS-485 transceiver with a GPIO around each
+//! transmission.
+//!
+//! # Synchronous mode
+//!
+//! [`SyncSerial`] uses a USART as an SPI master, with the clock on its CK pin.
+//!
+//! # Smartcards
+//!
+//! [`Smartcard`] talks to ISO 7816 cards using the T=0 protocol, with the card clock on the CK
+//! pin of a USART.
+//!
 
 use core::fmt;
 use core::marker::PhantomData;
@@ -31,17 +91,47 @@ use crate::gpio::{gpioa, gpiob, gpioc, gpiod, gpiog};
 
 use crate::pac::{RCC, UART4, UART5, USART1, USART2, USART3, USART6};
 
-use crate::gpio::NoPin;
+use crate::gpio::{NoPin, Pin, PinMode, PinState};
 use crate::rcc::Clocks;
+use crate::time::Bps;
 
 use crate::dma::traits::PeriAddress;
 
+mod buffered;
+pub use buffered::{BufferedInterrupt, BufferedRx, BufferedSerial, BufferedTx, ErrorCounts};
+mod dma;
+pub use dma::DmaRx;
+mod half_duplex;
+pub use half_duplex::HalfDuplex;
+mod io;
+pub mod lin;
+pub mod modbus;
+mod rs485;
+pub use rs485::Rs485;
+pub mod smartcard;
+pub use smartcard::Smartcard;
+mod synchronous;
+pub use synchronous::SyncSerial;
+#[cfg(feature = "async")]
+mod wait;
+#[cfg(feature = "async")]
+pub use wait::on_interrupt;
+
+// The rc_w0 flags of SR. Writing 1 leaves them untouched, so a flag is cleared without a
+// read-modify-write racing with the hardware setting the others.
+const SR_CTS: u32 = 1 << 9;
+const SR_LBD: u32 = 1 << 8;
+const SR_TC: u32 = 1 << 6;
+const SR_RXNE: u32 = 1 << 5;
+
 /// Serial error
 #[non_exhaustive]
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum Error {
     /// Framing error
     Framing,
+    /// Break received, a framing error on a word of zeros
+    Break,
     /// Noise error
     Noise,
     /// RX buffer overrun
@@ -58,12 +148,25 @@ pub enum Event {
     Txe,
     /// Idle line state detected
     Idle,
+    /// LIN break detected
+    LinBreak,
 }
 
 pub mod config {
     use crate::time::Bps;
     use crate::time::U32Ext;
 
+    /// Oversampling of the receiver
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Oversampling {
+        /// 16 times when the baud rate allows it, for the best noise tolerance, else 8 times
+        Auto,
+        /// 16 times, up to a 16th of the peripheral clock
+        By16,
+        /// 8 times, up to an 8th of the peripheral clock
+        By8,
+    }
+
     pub enum WordLength {
         DataBits8,
         DataBits9,
@@ -86,6 +189,30 @@ pub mod config {
         STOP1P5,
     }
 
+    /// Length of the break detected in LIN mode
+    pub enum LinBreakDetection {
+        /// 10 bit break detection
+        Bits10,
+        /// 11 bit break detection
+        Bits11,
+    }
+
+    /// Event that takes the receiver out of mute mode
+    pub enum Wakeup {
+        /// An idle line
+        IdleLine,
+        /// A word with the address mark, its MSB, set and the given 4 bit address
+        AddressMark(u8),
+    }
+
+    /// IrDA SIR pulse mode
+    pub enum IrdaMode {
+        /// Pulses of 3/16 bit time
+        Normal,
+        /// Pulses of 3 periods of the low-power clock, around 1.8432 MHz
+        LowPower,
+    }
+
     pub enum DmaConfig {
         None,
         Tx,
@@ -99,6 +226,12 @@ pub mod config {
         pub parity: Parity,
         pub stopbits: StopBits,
         pub dma: DmaConfig,
+        pub lin: Option<LinBreakDetection>,
+        pub wakeup: Wakeup,
+        pub oversampling: Oversampling,
+        /// Largest accepted difference between the configured and the actual baud rate, in
+        /// hundredths of a percent
+        pub baudrate_tolerance: u16,
     }
 
     impl Config {
@@ -136,6 +269,34 @@ pub mod config {
             self.stopbits = stopbits;
             self
         }
+
+        /// Enables LIN mode with the given break detection length
+        ///
+        /// LIN uses 8 data bits, no parity and one stop bit, which are set as well.
+        pub fn lin(mut self, detection: LinBreakDetection) -> Self {
+            self.wordlength = WordLength::DataBits8;
+            self.parity = Parity::ParityNone;
+            self.stopbits = StopBits::STOP1;
+            self.lin = Some(detection);
+            self
+        }
+
+        /// Selects how the receiver leaves mute mode
+        pub fn wakeup(mut self, wakeup: Wakeup) -> Self {
+            self.wakeup = wakeup;
+            self
+        }
+
+        pub fn oversampling(mut self, oversampling: Oversampling) -> Self {
+            self.oversampling = oversampling;
+            self
+        }
+
+        /// Sets the largest accepted baud rate error, in hundredths of a percent
+        pub fn baudrate_tolerance(mut self, tolerance: u16) -> Self {
+            self.baudrate_tolerance = tolerance;
+            self
+        }
     }
 
     #[derive(Debug)]
@@ -150,7 +311,143 @@ pub mod config {
                 parity: Parity::ParityNone,
                 stopbits: StopBits::STOP1,
                 dma: DmaConfig::None,
+                lin: None,
+                wakeup: Wakeup::IdleLine,
+                oversampling: Oversampling::Auto,
+                baudrate_tolerance: 200,
+            }
+        }
+    }
+
+    /// Baud rate divider of a UART/USART
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct BaudDivider {
+        /// Oversampling by 8 instead of 16
+        pub over8: bool,
+        /// BRR register value
+        pub brr: u16,
+        /// Resulting baud rate
+        pub baudrate: Bps,
+    }
+
+    impl BaudDivider {
+        /// Finds the divider giving the baud rate closest to `baudrate` from a `pclk` peripheral
+        /// clock
+        ///
+        /// The baud rate is `pclk / (8 x (2 - OVER8) x USARTDIV)`, where USARTDIV has 4
+        /// fractional bits when oversampling by 16 and 3 when oversampling by 8. Both give
+        /// `pclk / N` with an integer N, at least 16 or 8 respectively, so the automatic
+        /// oversampling only falls back to 8 times above a 16th of `pclk`.
+        ///
+        /// Returns `InvalidConfig` when the baud rate is out of reach or its error exceeds
+        /// `tolerance`, in hundredths of a percent.
+        pub fn new(
+            pclk: u32,
+            baudrate: u32,
+            oversampling: Oversampling,
+            tolerance: u16,
+        ) -> Result<Self, InvalidConfig> {
+            if baudrate == 0 {
+                return Err(InvalidConfig);
+            }
+            // Rounded to the closest divider rather than floored
+            let n = (u64::from(pclk) + u64::from(baudrate / 2)) / u64::from(baudrate);
+
+            let over8 = match oversampling {
+                Oversampling::By16 => false,
+                Oversampling::By8 => true,
+                Oversampling::Auto => n < 16,
+            };
+            // 12 bit mantissa
+            let (min, max) = if over8 { (8, 0x7FFF) } else { (16, 0xFFFF) };
+            if n < min || n > max {
+                return Err(InvalidConfig);
+            }
+            let n = n as u32;
+
+            let brr = if over8 {
+                // The 3 fractional bits are right aligned
+                ((n & !0x7) << 1) | (n & 0x7)
+            } else {
+                n
+            };
+            let divider = BaudDivider {
+                over8,
+                brr: brr as u16,
+                baudrate: Bps(pclk / n),
+            };
+
+            let actual = divider.baudrate.0;
+            let error = u64::from(actual.max(baudrate) - actual.min(baudrate)) * 10_000;
+            if error > u64::from(tolerance) * u64::from(baudrate) {
+                return Err(InvalidConfig);
             }
+            Ok(divider)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{BaudDivider, Oversampling};
+        use crate::time::Bps;
+
+        #[test]
+        fn over8_packs_the_fraction() {
+            // 60 MHz / 4 Mbit/s = 15: mantissa 1, fraction 7
+            let divider = BaudDivider::new(60_000_000, 4_000_000, Oversampling::By8, 0).unwrap();
+            assert!(divider.over8);
+            assert_eq!(divider.brr, 0x17);
+            assert_eq!(divider.baudrate, Bps(4_000_000));
+        }
+
+        #[test]
+        fn over16_keeps_the_divider() {
+            let divider = BaudDivider::new(60_000_000, 115_200, Oversampling::By16, 200).unwrap();
+            assert!(!divider.over8);
+            assert_eq!(divider.brr, 521);
+            assert_eq!(divider.baudrate, Bps(115_163));
+        }
+
+        #[test]
+        fn auto_switches_to_over8_below_16() {
+            let divider = BaudDivider::new(16_000_000, 1_000_000, Oversampling::Auto, 0).unwrap();
+            assert!(!divider.over8);
+            assert_eq!(divider.brr, 16);
+
+            let divider = BaudDivider::new(15_000_000, 1_000_000, Oversampling::Auto, 0).unwrap();
+            assert!(divider.over8);
+            assert_eq!(divider.brr, 0x17);
+        }
+
+        #[test]
+        fn reaches_7m5_at_60mhz() {
+            let divider = BaudDivider::new(60_000_000, 7_500_000, Oversampling::Auto, 0).unwrap();
+            assert!(divider.over8);
+            assert_eq!(divider.brr, 0x10);
+            assert_eq!(divider.baudrate, Bps(7_500_000));
+        }
+
+        #[test]
+        fn rejects_zero_baudrate() {
+            assert!(BaudDivider::new(60_000_000, 0, Oversampling::Auto, 200).is_err());
+        }
+
+        #[test]
+        fn rejects_out_of_range_divider() {
+            // 15 is below the minimum of oversampling by 16
+            assert!(BaudDivider::new(60_000_000, 4_000_000, Oversampling::By16, 200).is_err());
+            // Below 8 with any oversampling
+            assert!(BaudDivider::new(60_000_000, 10_000_000, Oversampling::Auto, 200).is_err());
+            // 60000 is above the maximum of oversampling by 8, not of 16
+            assert!(BaudDivider::new(60_000_000, 1_000, Oversampling::By8, 200).is_err());
+            assert!(BaudDivider::new(60_000_000, 1_000, Oversampling::By16, 200).is_ok());
+        }
+
+        #[test]
+        fn rejects_error_above_tolerance() {
+            // 60 MHz / 9 = 6.67 Mbit/s, 2.56 % above 6.5 Mbit/s
+            assert!(BaudDivider::new(60_000_000, 6_500_000, Oversampling::Auto, 200).is_err());
+            assert!(BaudDivider::new(60_000_000, 6_500_000, Oversampling::Auto, 300).is_ok());
         }
     }
 }
@@ -162,6 +459,18 @@ pub trait PinTx<USART> {
 pub trait PinRx<USART> {
     type A;
 }
+/// Clock output of the USARTs, used in synchronous and smartcard modes
+pub trait PinCk<USART> {
+    type A;
+}
+/// Request to send output of the USARTs
+pub trait PinRts<USART> {
+    type A;
+}
+/// Clear to send input of the USARTs
+pub trait PinCts<USART> {
+    type A;
+}
 
 impl<USART, TX, RX> Pins<USART> for (TX, RX)
 where
@@ -170,6 +479,15 @@ where
 {
 }
 
+impl<USART, TX, RX, RTS, CTS> Pins<USART> for (TX, RX, RTS, CTS)
+where
+    TX: PinTx<USART>,
+    RX: PinRx<USART>,
+    RTS: PinRts<USART>,
+    CTS: PinCts<USART>,
+{
+}
+
 /// A filler type for when the Tx pin is unnecessary
 pub type NoTx = NoPin;
 /// A filler type for when the Rx pin is unnecessary
@@ -204,14 +522,18 @@ pin!(PinRx<USART1> for gpiob::PB7<7>);
 
 pin!(PinTx<USART2> for gpioa::PA2<7>);
 pin!(PinRx<USART2> for gpioa::PA3<7>);
+#[cfg(not(feature = "lqfp64"))]
 pin!(PinTx<USART2> for gpiod::PD5<7>);
+#[cfg(not(feature = "lqfp64"))]
 pin!(PinRx<USART2> for gpiod::PD6<7>);
 
 pin!(PinTx<USART3> for gpiob::PB10<7>);
 pin!(PinRx<USART3> for gpiob::PB11<7>);
 pin!(PinTx<USART3> for gpioc::PC10<7>);
 pin!(PinRx<USART3> for gpioc::PC11<7>);
+#[cfg(not(feature = "lqfp64"))]
 pin!(PinTx<USART3> for gpiod::PD8<7>);
+#[cfg(not(feature = "lqfp64"))]
 pin!(PinRx<USART3> for gpiod::PD9<7>);
 
 pin!(PinTx<UART4> for gpioa::PA0<8>);
@@ -224,13 +546,57 @@ pin!(PinRx<UART5> for gpiod::PD2<8>);
 
 pin!(PinTx<USART6> for gpioc::PC6<8>);
 pin!(PinRx<USART6> for gpioc::PC7<8>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
 pin!(PinTx<USART6> for gpiog::PG14<8>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
 pin!(PinRx<USART6> for gpiog::PG9<8>);
 
+pin!(PinCk<USART1> for gpioa::PA8<7>);
+
+pin!(PinCk<USART2> for gpioa::PA4<7>);
+#[cfg(not(feature = "lqfp64"))]
+pin!(PinCk<USART2> for gpiod::PD7<7>);
+
+pin!(PinCk<USART3> for gpiob::PB12<7>);
+pin!(PinCk<USART3> for gpioc::PC12<7>);
+#[cfg(not(feature = "lqfp64"))]
+pin!(PinCk<USART3> for gpiod::PD10<7>);
+
+pin!(PinCk<USART6> for gpioc::PC8<8>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
+pin!(PinCk<USART6> for gpiog::PG7<8>);
+
+pin!(PinCts<USART1> for gpioa::PA11<7>);
+pin!(PinRts<USART1> for gpioa::PA12<7>);
+
+pin!(PinCts<USART2> for gpioa::PA0<7>);
+pin!(PinRts<USART2> for gpioa::PA1<7>);
+#[cfg(not(feature = "lqfp64"))]
+pin!(PinCts<USART2> for gpiod::PD3<7>);
+#[cfg(not(feature = "lqfp64"))]
+pin!(PinRts<USART2> for gpiod::PD4<7>);
+
+pin!(PinCts<USART3> for gpiob::PB13<7>);
+pin!(PinRts<USART3> for gpiob::PB14<7>);
+#[cfg(not(feature = "lqfp64"))]
+pin!(PinCts<USART3> for gpiod::PD11<7>);
+#[cfg(not(feature = "lqfp64"))]
+pin!(PinRts<USART3> for gpiod::PD12<7>);
+
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
+pin!(PinRts<USART6> for gpiog::PG8<8>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
+pin!(PinRts<USART6> for gpiog::PG12<8>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
+pin!(PinCts<USART6> for gpiog::PG13<8>);
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
+pin!(PinCts<USART6> for gpiog::PG15<8>);
+
 /// Serial abstraction
 pub struct Serial<USART, PINS, WORD = u8> {
     usart: USART,
     pins: PINS,
+    baudrate: Bps,
     tx: Tx<USART, WORD>,
     rx: Rx<USART, WORD>,
 }
@@ -301,6 +667,47 @@ where
             let _ = (*USART::ptr()).dr.read();
         }
     }
+
+    /// Start listening for a LIN break interrupt event
+    ///
+    /// Note, you will also have to enable the corresponding interrupt
+    /// in the NVIC to start receiving events.
+    pub fn listen_lin_break(&mut self) {
+        unsafe { (*USART::ptr()).cr2.modify(|_, w| w.lbdie().set_bit()) }
+    }
+
+    /// Stop listening for the LIN break interrupt event
+    pub fn unlisten_lin_break(&mut self) {
+        unsafe { (*USART::ptr()).cr2.modify(|_, w| w.lbdie().clear_bit()) }
+    }
+
+    /// Return true if a LIN break was detected
+    pub fn is_lin_break(&self) -> bool {
+        unsafe { (*USART::ptr()).sr.read().lbd().bit_is_set() }
+    }
+
+    /// Clear the LIN break detection flag
+    pub fn clear_lin_break(&mut self) {
+        // LBD written with 0, the other rc_w0 flags of the instance with 1
+        unsafe {
+            (*USART::ptr())
+                .sr
+                .write(|w| w.bits(USART::SR_RC_W0 & !SR_LBD))
+        }
+    }
+
+    /// Puts the receiver in mute mode, until the wakeup event selected in the `Config`
+    ///
+    /// In mute mode no word is received and no receive interrupt is raised. With address mark
+    /// wakeup, the word carrying the matching address is the first one received.
+    pub fn enter_mute(&mut self) {
+        unsafe { (*USART::ptr()).cr1.modify(|_, w| w.rwu().set_bit()) }
+    }
+
+    /// Return true while the receiver is in mute mode
+    pub fn is_muted(&self) -> bool {
+        unsafe { (*USART::ptr()).cr1.read().rwu().bit_is_set() }
+    }
 }
 
 impl<USART, WORD> Tx<USART, WORD>
@@ -331,6 +738,19 @@ where
     pub fn is_tx_empty(&self) -> bool {
         unsafe { (*USART::ptr()).sr.read().txe().bit_is_set() }
     }
+
+    /// Sends a break character after the current transmission
+    ///
+    /// In LIN mode the break is 13 bits long, otherwise it lasts one frame. The receiving side
+    /// reads it as [`Error::Break`].
+    pub fn send_break(&mut self) {
+        unsafe { (*USART::ptr()).cr1.modify(|_, w| w.sbk().set_bit()) }
+    }
+
+    /// Return true while the break requested by `send_break` is not sent yet
+    pub fn is_sending_break(&self) -> bool {
+        unsafe { (*USART::ptr()).cr1.read().sbk().bit_is_set() }
+    }
 }
 
 impl<USART, PINS, WORD> AsRef<Tx<USART, WORD>> for Serial<USART, PINS, WORD> {
@@ -376,6 +796,13 @@ where
     ) -> Result<Self, config::InvalidConfig> {
         use self::config::*;
 
+        let divider = BaudDivider::new(
+            USART::get_frequency(&clocks).0,
+            config.baudrate.0,
+            config.oversampling,
+            config.baudrate_tolerance,
+        )?;
+
         unsafe {
             // NOTE(unsafe) this reference will only be used for atomic writes with no side effects.
             let rcc = &(*RCC::ptr());
@@ -385,59 +812,7 @@ where
             USART::reset(rcc);
         }
 
-        let pclk_freq = USART::get_frequency(&clocks).0;
-        let baud = config.baudrate.0;
-
-        // The frequency to calculate USARTDIV is this:
-        //
-        // (Taken from STM32F411xC/E Reference Manual,
-        // Section 19.3.4, Equation 1)
-        //
-        // 16 bit oversample: OVER8 = 0
-        // 8 bit oversample:  OVER8 = 1
-        //
-        // USARTDIV =          (pclk)
-        //            ------------------------
-        //            8 x (2 - OVER8) x (baud)
-        //
-        // BUT, the USARTDIV has 4 "fractional" bits, which effectively
-        // means that we need to "correct" the equation as follows:
-        //
-        // USARTDIV =      (pclk) * 16
-        //            ------------------------
-        //            8 x (2 - OVER8) x (baud)
-        //
-        // When OVER8 is enabled, we can only use the lowest three
-        // fractional bits, so we'll need to shift those last four bits
-        // right one bit
-
-        // Calculate correct baudrate divisor on the fly
-        let (over8, div) = if (pclk_freq / 16) >= baud {
-            // We have the ability to oversample to 16 bits, take
-            // advantage of it.
-            //
-            // We also add `baud / 2` to the `pclk_freq` to ensure
-            // rounding of values to the closest scale, rather than the
-            // floored behavior of normal integer division.
-            let div = (pclk_freq + (baud / 2)) / baud;
-            (false, div)
-        } else if (pclk_freq / 8) >= baud {
-            // We are close enough to pclk where we can only
-            // oversample 8.
-            //
-            // See note above regarding `baud` and rounding.
-            let div = ((pclk_freq * 2) + (baud / 2)) / baud;
-
-            // Ensure the the fractional bits (only 3) are
-            // right-aligned.
-            let frac = div & 0xF;
-            let div = (div & !0xF) | (frac >> 1);
-            (true, div)
-        } else {
-            return Err(config::InvalidConfig);
-        };
-
-        unsafe { (*USART::ptr()).brr.write(|w| w.bits(div)) };
+        unsafe { (*USART::ptr()).brr.write(|w| w.bits(divider.brr.into())) };
 
         // Reset other registers to disable advanced USART features
         unsafe { (*USART::ptr()).cr2.reset() };
@@ -450,7 +825,7 @@ where
                 w.ue()
                     .set_bit()
                     .over8()
-                    .bit(over8)
+                    .bit(divider.over8)
                     .te()
                     .set_bit()
                     .re()
@@ -464,6 +839,8 @@ where
                     .bit(!matches!(config.parity, Parity::ParityNone))
                     .ps()
                     .bit(matches!(config.parity, Parity::ParityOdd))
+                    .wake()
+                    .bit(matches!(config.wakeup, Wakeup::AddressMark(_)))
             })
         };
 
@@ -484,10 +861,11 @@ where
         Ok(Serial {
             usart,
             pins,
+            baudrate: divider.baudrate,
             tx: Tx::new(),
             rx: Rx::new(),
         }
-        .config_stop(config))
+        .config_cr2(config))
     }
     pub fn release(mut self) -> (USART, (TX, RX)) {
         self.pins.0.restore_mode();
@@ -495,6 +873,164 @@ where
 
         (self.usart, self.pins)
     }
+
+    /// Creates a serial interface driving an IrDA SIR transceiver
+    ///
+    /// IrDA SIR runs up to 115200 baud with one stop bit. In low-power mode the prescaler is set
+    /// so that the low-power clock is as close as possible to 1.8432 MHz, it has to stay between
+    /// 1.42 and 2.12 MHz. Other configurations are rejected with `InvalidConfig`.
+    pub fn irda(
+        usart: USART,
+        pins: (TX, RX),
+        config: config::Config,
+        mode: config::IrdaMode,
+        clocks: Clocks,
+    ) -> Result<Self, config::InvalidConfig> {
+        use self::config::*;
+
+        if config.baudrate.0 > 115_200
+            || !matches!(config.stopbits, StopBits::STOP1)
+            || config.lin.is_some()
+        {
+            return Err(InvalidConfig);
+        }
+
+        let psc = match mode {
+            // The prescaler must be 1 outside of low-power mode
+            IrdaMode::Normal => 1,
+            IrdaMode::LowPower => {
+                let pclk_freq = USART::get_frequency(&clocks).0;
+                let psc = (pclk_freq + 921_600) / 1_843_200;
+                if psc == 0 || psc > 255 || !(1_420_000..=2_120_000).contains(&(pclk_freq / psc)) {
+                    return Err(InvalidConfig);
+                }
+                psc as u8
+            }
+        };
+
+        let serial = Self::new(usart, pins, config, clocks)?;
+
+        unsafe {
+            let usart = &*USART::ptr();
+            usart.cr1.modify(|_, w| w.ue().clear_bit());
+            // GTPR sits at the same place on the UARTs, which use its prescaler for IrDA only
+            (*(USART::ptr() as *const crate::pac::usart1::RegisterBlock))
+                .gtpr
+                .write(|w| w.psc().bits(psc));
+            usart.cr3.modify(|_, w| {
+                w.iren()
+                    .set_bit()
+                    .irlp()
+                    .bit(matches!(mode, IrdaMode::LowPower))
+            });
+            usart.cr1.modify(|_, w| w.ue().set_bit());
+        }
+
+        Ok(serial)
+    }
+}
+
+impl<
+        USART,
+        TX,
+        RX,
+        RTS,
+        CTS,
+        WORD,
+        const TXA: u8,
+        const RXA: u8,
+        const RTSA: u8,
+        const CTSA: u8,
+    > Serial<USART, (TX, RX, RTS, CTS), WORD>
+where
+    TX: PinTx<USART, A = Const<TXA>> + SetAlternate<TXA>,
+    RX: PinRx<USART, A = Const<RXA>> + SetAlternate<RXA>,
+    RTS: PinRts<USART, A = Const<RTSA>> + SetAlternate<RTSA>,
+    CTS: PinCts<USART, A = Const<CTSA>> + SetAlternate<CTSA>,
+    USART: UsartInstance,
+{
+    /// Creates a serial interface with hardware flow control
+    ///
+    /// RTS is asserted while the receiver can take a word and the transmitter waits for CTS
+    /// before sending each word.
+    pub fn with_flow_control(
+        usart: USART,
+        pins: (TX, RX, RTS, CTS),
+        config: config::Config,
+        clocks: Clocks,
+    ) -> Result<Self, config::InvalidConfig> {
+        let (tx, rx, mut rts, mut cts) = pins;
+        let Serial {
+            usart,
+            pins: (tx, rx),
+            baudrate,
+            tx: tx_half,
+            rx: rx_half,
+        } = Serial::new(usart, (tx, rx), config, clocks)?;
+
+        unsafe {
+            (*USART::usart_ptr())
+                .cr3
+                .modify(|_, w| w.rtse().set_bit().ctse().set_bit())
+        };
+
+        rts.set_alt_mode();
+        cts.set_alt_mode();
+
+        Ok(Serial {
+            usart,
+            pins: (tx, rx, rts, cts),
+            baudrate,
+            tx: tx_half,
+            rx: rx_half,
+        })
+    }
+
+    pub fn release(mut self) -> (USART, (TX, RX, RTS, CTS)) {
+        self.pins.0.restore_mode();
+        self.pins.1.restore_mode();
+        self.pins.2.restore_mode();
+        self.pins.3.restore_mode();
+
+        (self.usart, self.pins)
+    }
+}
+
+impl<USART, PINS, WORD> Serial<USART, PINS, WORD>
+where
+    USART: UsartInstance,
+{
+    /// Starts listening for changes of the CTS input
+    ///
+    /// Note, you will also have to enable the corresponding interrupt
+    /// in the NVIC to start receiving events.
+    pub fn listen_cts(&mut self) {
+        unsafe { (*USART::usart_ptr()).cr3.modify(|_, w| w.ctsie().set_bit()) }
+    }
+
+    /// Stop listening for changes of the CTS input
+    pub fn unlisten_cts(&mut self) {
+        unsafe {
+            (*USART::usart_ptr())
+                .cr3
+                .modify(|_, w| w.ctsie().clear_bit())
+        }
+    }
+
+    /// Return true if the CTS input changed
+    pub fn is_cts_changed(&self) -> bool {
+        unsafe { (*USART::usart_ptr()).sr.read().cts().bit_is_set() }
+    }
+
+    /// Clear the CTS change flag
+    pub fn clear_cts_interrupt(&mut self) {
+        // CTS written with 0, the other rc_w0 flags with 1
+        unsafe {
+            (*USART::usart_ptr())
+                .sr
+                .write(|w| w.bits(USART::SR_RC_W0 & !SR_CTS))
+        }
+    }
 }
 
 impl<USART, TX, WORD, const TXA: u8> Serial<USART, (TX, NoPin), WORD>
@@ -540,6 +1076,7 @@ where
             Event::Rxne => unsafe { (*USART::ptr()).cr1.modify(|_, w| w.rxneie().set_bit()) },
             Event::Txe => unsafe { (*USART::ptr()).cr1.modify(|_, w| w.txeie().set_bit()) },
             Event::Idle => unsafe { (*USART::ptr()).cr1.modify(|_, w| w.idleie().set_bit()) },
+            Event::LinBreak => unsafe { (*USART::ptr()).cr2.modify(|_, w| w.lbdie().set_bit()) },
         }
     }
 
@@ -549,6 +1086,7 @@ where
             Event::Rxne => unsafe { (*USART::ptr()).cr1.modify(|_, w| w.rxneie().clear_bit()) },
             Event::Txe => unsafe { (*USART::ptr()).cr1.modify(|_, w| w.txeie().clear_bit()) },
             Event::Idle => unsafe { (*USART::ptr()).cr1.modify(|_, w| w.idleie().clear_bit()) },
+            Event::LinBreak => unsafe { (*USART::ptr()).cr2.modify(|_, w| w.lbdie().clear_bit()) },
         }
     }
 
@@ -575,11 +1113,73 @@ where
         }
     }
 
+    /// Return true if a LIN break was detected
+    pub fn is_lin_break(&self) -> bool {
+        self.rx.is_lin_break()
+    }
+
+    /// Clear the LIN break detection flag
+    pub fn clear_lin_break(&mut self) {
+        self.rx.clear_lin_break()
+    }
+
+    /// Puts the receiver in mute mode, until the wakeup event selected in the `Config`
+    pub fn enter_mute(&mut self) {
+        self.rx.enter_mute()
+    }
+
+    /// Return true while the receiver is in mute mode
+    pub fn is_muted(&self) -> bool {
+        self.rx.is_muted()
+    }
+
+    /// Sends a break character after the current transmission
+    ///
+    /// In LIN mode the break is 13 bits long, otherwise it lasts one frame.
+    pub fn send_break(&mut self) {
+        self.tx.send_break()
+    }
+
+    /// Returns the baud rate actually programmed, which differs from the configured one by the
+    /// rounding of the divider
+    pub fn actual_baudrate(&self) -> Bps {
+        self.baudrate
+    }
+
     pub fn split(self) -> (Tx<USART, WORD>, Rx<USART, WORD>) {
         (self.tx, self.rx)
     }
 }
 
+impl<USART, RX, WORD, MODE, const P: char, const N: u8, const TXA: u8>
+    Serial<USART, (Pin<MODE, P, N>, RX), WORD>
+where
+    Pin<MODE, P, N>: PinTx<USART, A = Const<TXA>>,
+    MODE: PinMode,
+    USART: Instance,
+{
+    /// Sends a break of `break_us` microseconds followed by a mark of `mark_us`, by driving the
+    /// TX pin as a GPIO
+    ///
+    /// Unlike [`send_break`](Serial::send_break), the length of the break doesn't depend on the
+    /// frame length. DMX512 needs a break of at least 92 µs and a mark after break of at least
+    /// 12 µs, longer than a frame at 250 kbit/s. Waits for the end of the current transmission
+    /// first.
+    pub fn send_extended_break(&mut self, break_us: u32, mark_us: u32, clocks: &Clocks) {
+        // NOTE(unsafe) atomic read with no side effects
+        while unsafe { (*USART::ptr()).sr.read().tc().bit_is_clear() } {}
+
+        let cycles_per_us = clocks.sysclk().0 / 1_000_000;
+        self.pins
+            .0
+            .with_push_pull_output_then_alternate::<_, _, TXA>(PinState::Low, |pin| {
+                delay_us(break_us, cycles_per_us);
+                pin.set_high();
+                delay_us(mark_us, cycles_per_us);
+            });
+    }
+}
+
 impl<USART, PINS> Serial<USART, PINS, u8>
 where
     USART: Instance,
@@ -591,6 +1191,7 @@ where
         Serial {
             usart: self.usart,
             pins: self.pins,
+            baudrate: self.baudrate,
             tx: Tx::new(),
             rx: Rx::new(),
         }
@@ -608,6 +1209,7 @@ where
         Serial {
             usart: self.usart,
             pins: self.pins,
+            baudrate: self.baudrate,
             tx: Tx::new(),
             rx: Rx::new(),
         }
@@ -659,7 +1261,12 @@ where
             || sr.nf().bit_is_set()
             || sr.ore().bit_is_set()
         {
-            unsafe { (*USART::ptr()).dr.read() };
+            let data = unsafe { (*USART::ptr()).dr.read() }.dr().bits();
+
+            // The line held low for a whole frame, whatever the parity
+            if sr.fe().bit_is_set() && data == 0 {
+                return Err(Error::Break.into());
+            }
         }
 
         Err(if sr.pe().bit_is_set() {
@@ -736,6 +1343,24 @@ where
     }
 }
 
+impl<USART> Tx<USART, u16>
+where
+    USART: Instance,
+{
+    /// Writes a word with the address mark set, waking up the receivers in mute mode whose
+    /// address is the 4 LSBs of `address`
+    ///
+    /// The UART/USART must be configured with `WordLength::DataBits9`.
+    pub fn write_address(&mut self, address: u8) -> nb::Result<(), Error> {
+        self.write(0x100 | u16::from(address))
+    }
+
+    /// Writes a data word, with the address mark cleared
+    pub fn write_data(&mut self, data: u8) -> nb::Result<(), Error> {
+        self.write(u16::from(data))
+    }
+}
+
 /// Writes 9-bit words to the UART/USART
 ///
 /// If the UART/USART was configured with `WordLength::DataBits9`, the 9 least significant bits will
@@ -866,12 +1491,54 @@ impl<USART, PINS, WORD> Serial<USART, PINS, WORD>
 where
     USART: Instance,
 {
-    fn config_stop(self, config: config::Config) -> Self {
+    fn config_cr2(self, config: config::Config) -> Self {
         self.usart.set_stopbits(config.stopbits);
+        // LIN mode and the node address go on top of the stop bits written above
+        if let config::Wakeup::AddressMark(address) = config.wakeup {
+            unsafe {
+                (*USART::ptr())
+                    .cr2
+                    .modify(|_, w| w.add().bits(address & 0xF))
+            };
+        }
+        if let Some(detection) = config.lin {
+            unsafe {
+                (*USART::ptr()).cr2.modify(|_, w| {
+                    w.linen()
+                        .set_bit()
+                        .lbdl()
+                        .bit(matches!(detection, config::LinBreakDetection::Bits11))
+                })
+            };
+        }
         self
     }
 }
 
+/// Busy waits for `us` microseconds, one at a time so that long waits can't overflow the cycles
+fn delay_us(us: u32, cycles_per_us: u32) {
+    for _ in 0..us {
+        cortex_m::asm::delay(cycles_per_us);
+    }
+}
+
+/// Core clock cycles per bit at the baud rate programmed in BRR
+fn bit_cycles<USART: Instance>(clocks: &Clocks) -> u32 {
+    // NOTE(unsafe) atomic reads with no side effects
+    let usart = unsafe { &*USART::ptr() };
+    let brr = usart.brr.read().bits();
+    // 16 x USARTDIV, with the fraction of the 8 times oversampling back in 4 bits
+    let (div16, oversampling) = if usart.cr1.read().over8().bit_is_set() {
+        ((brr & !0xF) | ((brr & 0x7) << 1), 8)
+    } else {
+        (brr, 16)
+    };
+    let pclk = USART::get_frequency(clocks).0;
+    let cycles =
+        u64::from(clocks.sysclk().0) * u64::from(div16) * oversampling / 16 / u64::from(pclk);
+    cycles as u32
+}
+
 use crate::pac::uart4 as uart_base;
 
 // Implemented by all USART instances
@@ -880,11 +1547,32 @@ pub trait Instance: crate::Sealed + rcc::Enable + rcc::Reset + rcc::GetBusFreq {
     fn ptr() -> *const uart_base::RegisterBlock;
     #[doc(hidden)]
     fn set_stopbits(&self, bits: config::StopBits);
+    /// The rc_w0 flags the SR of the instance implements
+    #[doc(hidden)]
+    const SR_RC_W0: u32;
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    fn wakers() -> &'static wait::Wakers;
+}
+
+// Implemented by the USARTs, which have the clock output, smartcard and hardware flow control the
+// UARTs lack
+pub trait UsartInstance: Instance {
+    #[doc(hidden)]
+    fn usart_ptr() -> *const crate::pac::usart1::RegisterBlock;
 }
 
 macro_rules! halUsart {
     ($USARTX:ty) => {
+        impl UsartInstance for $USARTX {
+            fn usart_ptr() -> *const crate::pac::usart1::RegisterBlock {
+                <$USARTX>::ptr() as *const _
+            }
+        }
+
         impl Instance for $USARTX {
+            const SR_RC_W0: u32 = SR_CTS | SR_LBD | SR_TC | SR_RXNE;
+
             fn ptr() -> *const uart_base::RegisterBlock {
                 <$USARTX>::ptr() as *const _
             }
@@ -902,6 +1590,12 @@ macro_rules! halUsart {
                     })
                 });
             }
+
+            #[cfg(feature = "async")]
+            fn wakers() -> &'static wait::Wakers {
+                static WAKERS: wait::Wakers = wait::Wakers::new();
+                &WAKERS
+            }
         }
     };
 }
@@ -909,6 +1603,9 @@ macro_rules! halUsart {
 macro_rules! halUart {
     ($USARTX:ty) => {
         impl Instance for $USARTX {
+            // No CTS flag
+            const SR_RC_W0: u32 = SR_LBD | SR_TC | SR_RXNE;
+
             fn ptr() -> *const uart_base::RegisterBlock {
                 <$USARTX>::ptr() as *const _
             }
@@ -926,6 +1623,12 @@ macro_rules! halUart {
                     })
                 });
             }
+
+            #[cfg(feature = "async")]
+            fn wakers() -> &'static wait::Wakers {
+                static WAKERS: wait::Wakers = wait::Wakers::new();
+                &WAKERS
+            }
         }
     };
 }
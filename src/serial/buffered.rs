@@ -0,0 +1,303 @@
+//! Interrupt driven serial communication through queues
+
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embedded_hal::serial;
+use heapless::spsc::{Consumer, Producer, Queue};
+
+use super::{Instance, Rx, Tx};
+
+/// Receive errors counted by the interrupt handler
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct ErrorCounts {
+    /// Words lost, because the receive queue was full or the interrupt handler ran too late
+    pub overrun: u32,
+    /// Words dropped because of a framing error
+    pub framing: u32,
+    /// Words dropped because of a parity error
+    pub parity: u32,
+    /// Words received with noise, they are still queued
+    pub noise: u32,
+}
+
+#[derive(Default)]
+struct Counters {
+    overrun: AtomicU32,
+    framing: AtomicU32,
+    parity: AtomicU32,
+    noise: AtomicU32,
+}
+
+impl Counters {
+    fn get(&self) -> ErrorCounts {
+        ErrorCounts {
+            overrun: self.overrun.load(Ordering::Relaxed),
+            framing: self.framing.load(Ordering::Relaxed),
+            parity: self.parity.load(Ordering::Relaxed),
+            noise: self.noise.load(Ordering::Relaxed),
+        }
+    }
+
+    fn increment(counter: &AtomicU32) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Serial interface sending and receiving bytes through queues, from the USART interrupt
+///
+/// `RX` and `TX` are the sizes of the queues, which hold one byte less. The interrupt handler
+/// of the USART must call [`on_interrupt`](BufferedSerial::on_interrupt), it moves the received
+/// bytes to the receive queue and the queued bytes to the transmitter. Writes return
+/// `WouldBlock` when the transmit queue is full, bytes that can't be received are counted in
+/// [`errors`](BufferedSerial::errors).
+///
+/// All methods take `&mut self`, so sharing a `BufferedSerial` with the interrupt handler needs a
+/// lock. Placed in a static, it can instead be [`split`](BufferedSerial::split) into a reading,
+/// a writing and an interrupt half that don't need any.
+pub struct BufferedSerial<USART, const RX: usize, const TX: usize> {
+    tx: Tx<USART>,
+    rx: Rx<USART>,
+    rx_queue: Queue<u8, RX>,
+    tx_queue: Queue<u8, TX>,
+    counters: Counters,
+}
+
+/// Reading half of a [`BufferedSerial`]
+pub struct BufferedRx<'a, USART, const RX: usize> {
+    queue: Consumer<'a, u8, RX>,
+    counters: &'a Counters,
+    _usart: PhantomData<USART>,
+}
+
+/// Writing half of a [`BufferedSerial`]
+pub struct BufferedTx<'a, USART, const TX: usize> {
+    queue: Producer<'a, u8, TX>,
+    _usart: PhantomData<USART>,
+}
+
+/// Interrupt half of a [`BufferedSerial`], to move to the USART interrupt handler
+pub struct BufferedInterrupt<'a, USART, const RX: usize, const TX: usize> {
+    rx_queue: Producer<'a, u8, RX>,
+    tx_queue: Consumer<'a, u8, TX>,
+    counters: &'a Counters,
+    _usart: PhantomData<USART>,
+}
+
+impl<USART, const RX: usize, const TX: usize> BufferedSerial<USART, RX, TX>
+where
+    USART: Instance,
+{
+    /// Creates the queues and starts listening for received bytes
+    ///
+    /// The USART interrupt still has to be enabled in the NVIC.
+    pub fn new(tx: Tx<USART>, mut rx: Rx<USART>) -> Self {
+        rx.listen();
+        Self {
+            tx,
+            rx,
+            rx_queue: Queue::new(),
+            tx_queue: Queue::new(),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Stops the interrupts and releases the serial halves, the queued bytes are lost
+    pub fn release(mut self) -> (Tx<USART>, Rx<USART>) {
+        self.rx.unlisten();
+        self.tx.unlisten();
+        (self.tx, self.rx)
+    }
+
+    /// Splits the buffered serial into halves that can be used from different contexts
+    pub fn split(
+        &mut self,
+    ) -> (
+        BufferedRx<'_, USART, RX>,
+        BufferedTx<'_, USART, TX>,
+        BufferedInterrupt<'_, USART, RX, TX>,
+    ) {
+        let (rx_producer, rx_consumer) = self.rx_queue.split();
+        let (tx_producer, tx_consumer) = self.tx_queue.split();
+        (
+            BufferedRx {
+                queue: rx_consumer,
+                counters: &self.counters,
+                _usart: PhantomData,
+            },
+            BufferedTx {
+                queue: tx_producer,
+                _usart: PhantomData,
+            },
+            BufferedInterrupt {
+                rx_queue: rx_producer,
+                tx_queue: tx_consumer,
+                counters: &self.counters,
+                _usart: PhantomData,
+            },
+        )
+    }
+
+    /// Handles the USART interrupt
+    pub fn on_interrupt(&mut self) {
+        self.split().2.on_interrupt()
+    }
+
+    /// Returns the receive errors counted so far
+    pub fn errors(&self) -> ErrorCounts {
+        self.counters.get()
+    }
+}
+
+impl<'a, USART, const RX: usize, const TX: usize> BufferedInterrupt<'a, USART, RX, TX>
+where
+    USART: Instance,
+{
+    /// Handles the USART interrupt
+    pub fn on_interrupt(&mut self) {
+        // NOTE(unsafe) the flags are only cleared here, by reading DR
+        let usart = unsafe { &*USART::ptr() };
+        let sr = usart.sr.read();
+
+        if sr.rxne().bit_is_set() || sr.ore().bit_is_set() {
+            // The error flags belong to the word in DR, which also clears them
+            let byte = usart.dr.read().dr().bits() as u8;
+            if sr.ore().bit_is_set() {
+                Counters::increment(&self.counters.overrun);
+            }
+            if sr.fe().bit_is_set() {
+                Counters::increment(&self.counters.framing);
+            } else if sr.pe().bit_is_set() {
+                Counters::increment(&self.counters.parity);
+            } else {
+                if sr.nf().bit_is_set() {
+                    Counters::increment(&self.counters.noise);
+                }
+                if self.rx_queue.enqueue(byte).is_err() {
+                    Counters::increment(&self.counters.overrun);
+                }
+            }
+        }
+
+        if sr.txe().bit_is_set() && usart.cr1.read().txeie().bit_is_set() {
+            match self.tx_queue.dequeue() {
+                Some(byte) => usart.dr.write(|w| w.dr().bits(u16::from(byte))),
+                None => cortex_m::interrupt::free(|_| {
+                    // A write may have queued a byte since
+                    if !self.tx_queue.ready() {
+                        usart.cr1.modify(|_, w| w.txeie().clear_bit());
+                    }
+                }),
+            }
+        }
+    }
+
+    /// Returns the receive errors counted so far
+    pub fn errors(&self) -> ErrorCounts {
+        self.counters.get()
+    }
+}
+
+impl<'a, USART, const RX: usize> BufferedRx<'a, USART, RX>
+where
+    USART: Instance,
+{
+    /// Returns the number of bytes waiting in the receive queue
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if no byte is waiting in the receive queue
+    pub fn is_empty(&self) -> bool {
+        !self.queue.ready()
+    }
+
+    /// Returns the receive errors counted so far
+    pub fn errors(&self) -> ErrorCounts {
+        self.counters.get()
+    }
+}
+
+impl<'a, USART, const RX: usize> serial::Read<u8> for BufferedRx<'a, USART, RX>
+where
+    USART: Instance,
+{
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        self.queue.dequeue().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<'a, USART, const TX: usize> serial::Write<u8> for BufferedTx<'a, USART, TX>
+where
+    USART: Instance,
+{
+    type Error = Infallible;
+
+    /// Queues a byte, returns `WouldBlock` while the transmit queue is full
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        self.queue
+            .enqueue(byte)
+            .map_err(|_| nb::Error::WouldBlock)?;
+        // NOTE(unsafe) the interrupt handler only clears TXEIE inside a critical section
+        cortex_m::interrupt::free(|_| unsafe {
+            (*USART::ptr()).cr1.modify(|_, w| w.txeie().set_bit())
+        });
+        Ok(())
+    }
+
+    /// Waits until the transmit queue is empty and the last byte is sent
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        // NOTE(unsafe) atomic read with no side effects
+        let sr = unsafe { (*USART::ptr()).sr.read() };
+        if self.queue.len() == 0 && sr.tc().bit_is_set() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<'a, USART, const TX: usize> embedded_hal::blocking::serial::write::Default<u8>
+    for BufferedTx<'a, USART, TX>
+where
+    USART: Instance,
+{
+}
+
+impl<USART, const RX: usize, const TX: usize> serial::Read<u8> for BufferedSerial<USART, RX, TX>
+where
+    USART: Instance,
+{
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        serial::Read::read(&mut self.split().0)
+    }
+}
+
+impl<USART, const RX: usize, const TX: usize> serial::Write<u8> for BufferedSerial<USART, RX, TX>
+where
+    USART: Instance,
+{
+    type Error = Infallible;
+
+    /// Queues a byte, returns `WouldBlock` while the transmit queue is full
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        serial::Write::write(&mut self.split().1, byte)
+    }
+
+    /// Waits until the transmit queue is empty and the last byte is sent
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        serial::Write::flush(&mut self.split().1)
+    }
+}
+
+impl<USART, const RX: usize, const TX: usize> embedded_hal::blocking::serial::write::Default<u8>
+    for BufferedSerial<USART, RX, TX>
+where
+    USART: Instance,
+{
+}
@@ -0,0 +1,130 @@
+//! DMA reception of variable length frames delimited by an idle line
+
+use core::slice;
+
+use embedded_dma::StaticWriteBuffer;
+
+use super::{Error, Instance, Rx};
+use crate::dma::config::DmaConfig;
+use crate::dma::traits::{Channel, DMASet, Stream};
+use crate::dma::{ChannelX, PeripheralToMemory, Transfer};
+
+/// Serial receiver that collects frames with DMA
+///
+/// Received bytes are written into one of two buffers by the DMA. A frame ends when the line
+/// goes idle or when the buffer is full, the buffers are then swapped and the frame is handed out
+/// as a slice of the buffer it was received in, without copying. The slice stays valid until the
+/// next call to [`read_frame`](Self::read_frame).
+///
+/// Use [`listen_idle`](Self::listen_idle) and the transfer complete interrupt of the stream to
+/// get an interrupt at the end of every frame.
+pub struct DmaRx<USART, STREAM, BUF, const CHANNEL: u8>
+where
+    USART: Instance,
+    STREAM: Stream,
+{
+    transfer: Transfer<STREAM, Rx<USART>, PeripheralToMemory, BUF, CHANNEL>,
+    // Holds the last frame handed out, becomes the DMA buffer on the next swap
+    spare: Option<BUF>,
+    buf_len: u16,
+}
+
+impl<USART, STREAM, BUF, const CHANNEL: u8> DmaRx<USART, STREAM, BUF, CHANNEL>
+where
+    USART: Instance,
+    STREAM: Stream,
+    ChannelX<CHANNEL>: Channel,
+    Rx<USART>: DMASet<STREAM, PeripheralToMemory, CHANNEL>,
+    BUF: StaticWriteBuffer<Word = u8>,
+{
+    /// Starts receiving into `buf` with `spare` ready for the next frame
+    ///
+    /// Memory increment is always enabled and double buffering always disabled, the rest of
+    /// `config` is applied as is.
+    pub fn new(rx: Rx<USART>, stream: STREAM, mut buf: BUF, spare: BUF, config: DmaConfig) -> Self {
+        // NOTE(unsafe) The buffer is owned by the transfer until it is released
+        let (_, buf_len) = unsafe { buf.write_buffer() };
+
+        unsafe { (*USART::ptr()).cr3.modify(|_, w| w.dmar().enabled()) };
+
+        let config = config.memory_increment(true).double_buffer(false);
+        let mut transfer = Transfer::init_peripheral_to_memory(stream, rx, buf, None, config);
+        transfer.start(|rx| rx.clear_idle_interrupt());
+
+        Self {
+            transfer,
+            spare: Some(spare),
+            buf_len: buf_len as u16,
+        }
+    }
+
+    /// Start listening for a line idle interrupt event
+    ///
+    /// Note, you will also have to enable the corresponding interrupt
+    /// in the NVIC to start receiving events.
+    pub fn listen_idle(&mut self) {
+        unsafe { (*USART::ptr()).cr1.modify(|_, w| w.idleie().set_bit()) }
+    }
+
+    /// Stop listening for the line idle interrupt event
+    pub fn unlisten_idle(&mut self) {
+        unsafe { (*USART::ptr()).cr1.modify(|_, w| w.idleie().clear_bit()) }
+    }
+
+    /// Returns the next complete frame
+    ///
+    /// A frame is complete once the line went idle after at least one byte or the buffer is
+    /// full. If the USART lost data because of an overrun, the bytes received so far are
+    /// discarded and `Error::Overrun` is returned.
+    pub fn read_frame(&mut self) -> nb::Result<&[u8], Error> {
+        // NOTE(unsafe) atomic read with no side effects
+        let sr = unsafe { (*USART::ptr()).sr.read() };
+        let full = STREAM::get_transfer_complete_flag();
+        let overrun = sr.ore().bit_is_set();
+
+        if !full && !overrun && !sr.idle().bit_is_set() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // Stop the stream so the frame can't grow while it is handed out
+        self.transfer.pause(|_| {});
+        let frame_len = usize::from(self.buf_len - STREAM::get_number_of_transfers());
+
+        // IDLE and ORE are cleared by reading SR and then DR. If a byte of the next frame is
+        // already waiting, the DMA completes the sequence when it reads DR after the restart.
+        if !sr.rxne().bit_is_set() {
+            unsafe { (*USART::ptr()).dr.read() };
+        }
+
+        if frame_len == 0 && !overrun {
+            // Idle without data, keep the current buffer
+            // NOTE(unsafe) The stream is configured and only paused
+            unsafe { self.transfer.get_stream().enable() };
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // Never empty, the spare buffer is put back right below
+        let mut spare = self.spare.take().unwrap();
+        // NOTE(unsafe) The buffer is owned by the transfer until it is swapped out again
+        self.buf_len = unsafe { spare.write_buffer() }.1 as u16;
+        // Never fails without double buffering
+        let (mut frame, _) = self.transfer.next_transfer(spare).ok().unwrap();
+        let frame_ptr = unsafe { frame.write_buffer() }.0 as *const u8;
+        self.spare = Some(frame);
+
+        if overrun {
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+
+        // NOTE(unsafe) The DMA doesn't write to this buffer until the next swap
+        Ok(unsafe { slice::from_raw_parts(frame_ptr, frame_len) })
+    }
+
+    /// Stops the stream and returns the receiver, the stream and both buffers
+    pub fn release(mut self) -> (Rx<USART>, STREAM, BUF, BUF) {
+        let spare = self.spare.take().unwrap();
+        let (stream, rx, buf, _) = self.transfer.release();
+        unsafe { (*USART::ptr()).cr3.modify(|_, w| w.dmar().disabled()) };
+        (rx, stream, buf, spare)
+    }
+}
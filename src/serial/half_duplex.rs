@@ -0,0 +1,130 @@
+//! Single wire half duplex communication
+
+use embedded_hal::blocking;
+use embedded_hal::serial;
+
+use super::config::{Config, InvalidConfig};
+use super::{Error, Instance, PinTx, Rx, Serial, Tx};
+use crate::gpio::{Const, NoPin, SetAlternateOD};
+use crate::rcc::Clocks;
+
+/// Serial interface on a single open drain wire, the TX pin
+///
+/// Created by [`Serial::half_duplex`]. The receiver is disabled from the first word written
+/// until the transmission is complete, after [`flush`](serial::Write::flush) or on the next
+/// read, so the local echo of the transmitted words is never read. The line needs a pull-up.
+pub struct HalfDuplex<USART, TX, WORD = u8> {
+    serial: Serial<USART, (NoPin, NoPin), WORD>,
+    pin: TX,
+    transmitting: bool,
+}
+
+impl<USART, TX, WORD, const TXA: u8> Serial<USART, (TX, NoPin), WORD>
+where
+    TX: PinTx<USART, A = Const<TXA>> + SetAlternateOD<TXA>,
+    USART: Instance,
+{
+    /// Creates a half duplex serial interface on the TX pin, in open drain mode
+    ///
+    /// LIN mode can't be combined with half duplex, `InvalidConfig` is returned for it.
+    pub fn half_duplex(
+        usart: USART,
+        mut tx_pin: TX,
+        config: Config,
+        clocks: Clocks,
+    ) -> Result<HalfDuplex<USART, TX, WORD>, InvalidConfig> {
+        if config.lin.is_some() {
+            return Err(InvalidConfig);
+        }
+
+        let serial = Serial::new(usart, (NoPin, NoPin), config, clocks)?;
+        unsafe { (*USART::ptr()).cr3.modify(|_, w| w.hdsel().set_bit()) };
+
+        tx_pin.set_alt_mode();
+
+        Ok(HalfDuplex {
+            serial,
+            pin: tx_pin,
+            transmitting: false,
+        })
+    }
+}
+
+impl<USART, TX, WORD, const TXA: u8> HalfDuplex<USART, TX, WORD>
+where
+    TX: PinTx<USART, A = Const<TXA>> + SetAlternateOD<TXA>,
+    USART: Instance,
+{
+    pub fn release(mut self) -> (USART, TX) {
+        self.pin.restore_mode();
+
+        (self.serial.usart, self.pin)
+    }
+}
+
+impl<USART, TX, WORD> HalfDuplex<USART, TX, WORD>
+where
+    USART: Instance,
+{
+    /// Returns true while words are being sent and the receiver is disabled
+    pub fn is_transmitting(&self) -> bool {
+        self.transmitting
+    }
+
+    fn end_transmission(&mut self) {
+        unsafe { (*USART::ptr()).cr1.modify(|_, w| w.re().set_bit()) };
+        self.transmitting = false;
+    }
+}
+
+impl<USART, TX, WORD> serial::Write<WORD> for HalfDuplex<USART, TX, WORD>
+where
+    USART: Instance,
+    Tx<USART, WORD>: serial::Write<WORD, Error = Error>,
+{
+    type Error = Error;
+
+    fn write(&mut self, word: WORD) -> nb::Result<(), Error> {
+        if !self.transmitting {
+            unsafe { (*USART::ptr()).cr1.modify(|_, w| w.re().clear_bit()) };
+            self.transmitting = true;
+        }
+        serial::Write::write(&mut self.serial, word)
+    }
+
+    /// Waits for the end of the transmission, then switches back to reception
+    fn flush(&mut self) -> nb::Result<(), Error> {
+        serial::Write::flush(&mut self.serial)?;
+        if self.transmitting {
+            self.end_transmission();
+        }
+        Ok(())
+    }
+}
+
+impl<USART, TX, WORD> blocking::serial::write::Default<WORD> for HalfDuplex<USART, TX, WORD>
+where
+    USART: Instance,
+    Tx<USART, WORD>: serial::Write<WORD, Error = Error>,
+{
+}
+
+impl<USART, TX, WORD> serial::Read<WORD> for HalfDuplex<USART, TX, WORD>
+where
+    USART: Instance,
+    Rx<USART, WORD>: serial::Read<WORD, Error = Error>,
+{
+    type Error = Error;
+
+    /// Reads a word, once the transmission in progress is complete
+    fn read(&mut self) -> nb::Result<WORD, Error> {
+        if self.transmitting {
+            // NOTE(unsafe) atomic read with no side effects
+            if unsafe { (*USART::ptr()).sr.read().tc().bit_is_clear() } {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.end_transmission();
+        }
+        serial::Read::read(&mut self.serial)
+    }
+}
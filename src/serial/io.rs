@@ -0,0 +1,166 @@
+//! `embedded-io` traits
+
+use embedded_hal::serial;
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write, WriteReady};
+use nb::block;
+
+use super::{Error, Instance, Rx, Serial, Tx};
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Overrun => ErrorKind::Other,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl<USART> Rx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Stores `first` then the bytes already received into `buf`, returns the number of bytes
+    /// stored
+    pub(super) fn read_received(&mut self, first: u8, buf: &mut [u8]) -> Result<usize, Error> {
+        buf[0] = first;
+        for (n, byte) in buf.iter_mut().enumerate().skip(1) {
+            match serial::Read::read(self) {
+                Ok(b) => *byte = b,
+                Err(nb::Error::WouldBlock) => return Ok(n),
+                Err(nb::Error::Other(error)) => return Err(error),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    /// Returns true if a read won't block, because a byte or an error is pending
+    fn is_read_ready(&self) -> bool {
+        // NOTE(unsafe) atomic read with no side effects
+        let sr = unsafe { (*USART::ptr()).sr.read() };
+        sr.rxne().bit_is_set()
+            || sr.ore().bit_is_set()
+            || sr.fe().bit_is_set()
+            || sr.pe().bit_is_set()
+            || sr.nf().bit_is_set()
+    }
+}
+
+impl<USART> Tx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Sends the first byte of `buf`, which the transmitter must be ready for, then as many of
+    /// the next ones as it accepts without waiting. Returns the number of bytes sent.
+    pub(super) fn write_accepted(&mut self, buf: &[u8]) -> usize {
+        for (n, &byte) in buf.iter().enumerate() {
+            if n != 0 && !self.is_tx_empty() {
+                return n;
+            }
+            // NOTE(unsafe) atomic write to stateless register
+            unsafe { (*USART::ptr()).dr.write(|w| w.dr().bits(u16::from(byte))) };
+        }
+        buf.len()
+    }
+}
+
+impl<USART> ErrorType for Rx<USART, u8> {
+    type Error = Error;
+}
+
+impl<USART> ErrorType for Tx<USART, u8> {
+    type Error = Error;
+}
+
+impl<USART, PINS> ErrorType for Serial<USART, PINS, u8> {
+    type Error = Error;
+}
+
+impl<USART> Read for Rx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Waits for a byte, then reads the bytes already received up to the length of `buf`
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let first = block!(serial::Read::read(self))?;
+        self.read_received(first, buf)
+    }
+}
+
+impl<USART> ReadReady for Rx<USART, u8>
+where
+    USART: Instance,
+{
+    fn read_ready(&mut self) -> Result<bool, Error> {
+        Ok(self.is_read_ready())
+    }
+}
+
+impl<USART> Write for Tx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Waits until the transmitter accepts a byte, then sends as many bytes as it accepts
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while !self.is_tx_empty() {}
+        Ok(self.write_accepted(buf))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        block!(serial::Write::flush(self))
+    }
+}
+
+impl<USART> WriteReady for Tx<USART, u8>
+where
+    USART: Instance,
+{
+    fn write_ready(&mut self) -> Result<bool, Error> {
+        Ok(self.is_tx_empty())
+    }
+}
+
+impl<USART, PINS> Read for Serial<USART, PINS, u8>
+where
+    USART: Instance,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Read::read(&mut self.rx, buf)
+    }
+}
+
+impl<USART, PINS> ReadReady for Serial<USART, PINS, u8>
+where
+    USART: Instance,
+{
+    fn read_ready(&mut self) -> Result<bool, Error> {
+        self.rx.read_ready()
+    }
+}
+
+impl<USART, PINS> Write for Serial<USART, PINS, u8>
+where
+    USART: Instance,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(&mut self.tx, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Write::flush(&mut self.tx)
+    }
+}
+
+impl<USART, PINS> WriteReady for Serial<USART, PINS, u8>
+where
+    USART: Instance,
+{
+    fn write_ready(&mut self) -> Result<bool, Error> {
+        self.tx.write_ready()
+    }
+}
@@ -0,0 +1,273 @@
+//! LIN frame layer
+//!
+//! [`Lin`] runs LIN frames on a [`Serial`] configured with
+//! [`Config::lin`](super::config::Config::lin), connected to a LIN transceiver. The bus is a
+//! single wire, so every byte sent is received back and compared to detect collisions.
+//!
+//! A master sends the header and either sends or receives the response with
+//! [`write_frame`](Lin::write_frame) and [`read_frame`](Lin::read_frame). A slave polls for
+//! headers with [`read_header`](Lin::read_header), possibly from the
+//! [`LinBreak`](super::Event::LinBreak) interrupt, then answers with
+//! [`write_response`](Lin::write_response) or receives the response of another node with
+//! [`read_response`](Lin::read_response).
+//!
+//! Timeouts follow the maximum frame times of the LIN specification, 1.4 times the nominal
+//! length.
+
+use embedded_hal::serial;
+use nb::block;
+
+use super::{bit_cycles, Instance, Serial};
+use crate::rcc::Clocks;
+
+/// Byte following the break of every header
+pub const SYNC: u8 = 0x55;
+
+/// Maximum number of data bytes in a frame
+pub const MAX_DATA_LEN: usize = 8;
+
+/// LIN error
+#[non_exhaustive]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+    /// Error reported by the USART
+    Serial(super::Error),
+    /// The expected byte or break did not arrive in time
+    Timeout,
+    /// A byte read back from the bus differs from the byte sent
+    BitError,
+    /// The byte following the break is not [`SYNC`]
+    Sync,
+    /// The parity bits of the protected identifier are wrong
+    Parity,
+    /// The checksum of the response is wrong
+    Checksum,
+}
+
+impl From<super::Error> for Error {
+    fn from(error: super::Error) -> Self {
+        Error::Serial(error)
+    }
+}
+
+/// Checksum model
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Checksum {
+    /// Data bytes only, LIN 1.x
+    Classic,
+    /// Protected identifier and data bytes, LIN 2.x. The diagnostic frames 0x3C and 0x3D always
+    /// use the classic checksum.
+    Enhanced,
+}
+
+/// Returns the protected identifier of the 6 bit frame identifier `id`
+pub fn protected_id(id: u8) -> u8 {
+    let id = id & 0x3F;
+    let bit = |n: u8| (id >> n) & 1;
+    let p0 = bit(0) ^ bit(1) ^ bit(2) ^ bit(4);
+    let p1 = !(bit(1) ^ bit(3) ^ bit(4) ^ bit(5)) & 1;
+    id | (p0 << 6) | (p1 << 7)
+}
+
+/// Returns the frame identifier of the protected identifier `pid`, if its parity bits are right
+pub fn frame_id(pid: u8) -> Option<u8> {
+    let id = pid & 0x3F;
+    if protected_id(id) == pid {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Computes the checksum of a frame from its protected identifier and data
+pub fn checksum(model: Checksum, pid: u8, data: &[u8]) -> u8 {
+    let id = pid & 0x3F;
+    let init = match model {
+        Checksum::Enhanced if id != 0x3C && id != 0x3D => u16::from(pid),
+        _ => 0,
+    };
+    // Sum with the carry added back
+    let sum = data.iter().fold(init, |sum, &byte| {
+        let sum = sum + u16::from(byte);
+        if sum > 0xFF {
+            sum - 0xFF
+        } else {
+            sum
+        }
+    });
+    !(sum as u8)
+}
+
+/// LIN master or slave node on a USART
+pub struct Lin<USART, PINS> {
+    serial: Serial<USART, PINS>,
+    checksum: Checksum,
+    // Core clock cycles per bit
+    bit_cycles: u32,
+}
+
+impl<USART, PINS> Lin<USART, PINS>
+where
+    USART: Instance,
+{
+    /// Runs LIN frames using the `checksum` model on `serial`, which must be in LIN mode
+    pub fn new(serial: Serial<USART, PINS>, checksum: Checksum, clocks: Clocks) -> Self {
+        Self {
+            serial,
+            checksum,
+            bit_cycles: bit_cycles::<USART>(&clocks),
+        }
+    }
+
+    /// Releases the serial interface
+    pub fn release(self) -> Serial<USART, PINS> {
+        self.serial
+    }
+
+    /// Sends the header of frame `id`: break, sync byte and protected identifier
+    pub fn send_header(&mut self, id: u8) -> Result<(), Error> {
+        block!(serial::Write::flush(&mut self.serial))?;
+        self.discard_rx();
+        self.serial.clear_lin_break();
+
+        self.serial.send_break();
+        // Read the break back, 13 bits plus the 1 bit break delimiter, with margin
+        self.wait_break(20)?;
+
+        self.write_byte(SYNC)?;
+        self.write_byte(protected_id(id))
+    }
+
+    /// Polls for a header sent by the master and returns its frame identifier
+    ///
+    /// Returns `WouldBlock` until a break is detected, then waits for the rest of the header.
+    pub fn read_header(&mut self) -> nb::Result<u8, Error> {
+        if !self.serial.is_lin_break() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.serial.clear_lin_break();
+
+        // Sync byte and protected identifier within the header time, minus the break. When
+        // polled late, the sync byte may already be pending behind the break.
+        let mut budget = 28;
+        let sync = match self.take_break_byte() {
+            Some(byte) => byte,
+            None => self.read_byte(&mut budget)?,
+        };
+        if sync != SYNC {
+            return Err(nb::Error::Other(Error::Sync));
+        }
+        let pid = self.read_byte(&mut budget)?;
+        frame_id(pid).ok_or(nb::Error::Other(Error::Parity))
+    }
+
+    /// Sends the response to frame `id`: `data` and its checksum
+    ///
+    /// # Panics
+    ///
+    /// * When `data` is longer than [`MAX_DATA_LEN`].
+    pub fn write_response(&mut self, id: u8, data: &[u8]) -> Result<(), Error> {
+        assert!(data.len() <= MAX_DATA_LEN, "LIN frames carry up to 8 bytes");
+
+        for &byte in data {
+            self.write_byte(byte)?;
+        }
+        self.write_byte(checksum(self.checksum, protected_id(id), data))
+    }
+
+    /// Receives the response to frame `id` into `data` and checks its checksum
+    ///
+    /// # Panics
+    ///
+    /// * When `data` is longer than [`MAX_DATA_LEN`].
+    pub fn read_response(&mut self, id: u8, data: &mut [u8]) -> Result<(), Error> {
+        assert!(data.len() <= MAX_DATA_LEN, "LIN frames carry up to 8 bytes");
+
+        let mut budget = 14 * (data.len() as u32 + 1);
+        for byte in data.iter_mut() {
+            *byte = self.read_byte(&mut budget)?;
+        }
+        let received = self.read_byte(&mut budget)?;
+        if received == checksum(self.checksum, protected_id(id), data) {
+            Ok(())
+        } else {
+            Err(Error::Checksum)
+        }
+    }
+
+    /// Master: sends frame `id` with `data` as response
+    pub fn write_frame(&mut self, id: u8, data: &[u8]) -> Result<(), Error> {
+        self.send_header(id)?;
+        self.write_response(id, data)
+    }
+
+    /// Master: sends the header of frame `id` and receives the response of a slave into `data`
+    pub fn read_frame(&mut self, id: u8, data: &mut [u8]) -> Result<(), Error> {
+        self.send_header(id)?;
+        self.read_response(id, data)
+    }
+
+    /// Sends a byte and reads it back from the bus
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        block!(serial::Write::write(&mut self.serial, byte))?;
+        // One frame of 10 bits, with margin
+        if self.read_byte(&mut 14)? == byte {
+            Ok(())
+        } else {
+            Err(Error::BitError)
+        }
+    }
+
+    /// Waits up to `budget` bit times for a byte, decrementing the budget while waiting
+    fn read_byte(&mut self, budget: &mut u32) -> Result<u8, Error> {
+        loop {
+            match serial::Read::read(&mut self.serial) {
+                Ok(byte) => return Ok(byte),
+                Err(nb::Error::Other(error)) => return Err(Error::Serial(error)),
+                Err(nb::Error::WouldBlock) if *budget == 0 => return Err(Error::Timeout),
+                Err(nb::Error::WouldBlock) => {
+                    *budget -= 1;
+                    cortex_m::asm::delay(self.bit_cycles);
+                }
+            }
+        }
+    }
+
+    fn wait_break(&mut self, mut budget: u32) -> Result<(), Error> {
+        while !self.serial.is_lin_break() {
+            if budget == 0 {
+                return Err(Error::Timeout);
+            }
+            budget -= 1;
+            cortex_m::asm::delay(self.bit_cycles);
+        }
+        self.serial.clear_lin_break();
+        // The break is also received as a 0 byte with a framing error
+        self.discard_rx();
+        Ok(())
+    }
+
+    /// Drops the 0 byte the break is received as, returns the pending byte if it is another one
+    fn take_break_byte(&mut self) -> Option<u8> {
+        // NOTE(unsafe) reading SR then DR clears RXNE and the error flags
+        let usart = unsafe { &*USART::ptr() };
+        let sr = usart.sr.read();
+        if sr.rxne().bit_is_clear() {
+            return None;
+        }
+        let byte = usart.dr.read().dr().bits() as u8;
+        if sr.fe().bit_is_set() || byte == 0 {
+            None
+        } else {
+            Some(byte)
+        }
+    }
+
+    fn discard_rx(&mut self) {
+        // NOTE(unsafe) reading SR then DR clears RXNE and the error flags
+        unsafe {
+            let _ = (*USART::ptr()).sr.read();
+            let _ = (*USART::ptr()).dr.read();
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Modbus RTU framing layer
+//!
+//! [`Rtu`] sends and receives Modbus RTU application data units (ADUs) on a serial interface,
+//! usually an [`Rs485`](super::Rs485) or a [`Serial`](super::Serial). Frames are delimited by
+//! silent intervals measured with a hardware timer, which ticks every half character:
+//!
+//! * a frame ends after 3.5 characters without reception,
+//! * a gap of more than 1.5 characters inside a frame makes it invalid.
+//!
+//! Above 19200 bit/s the specification fixes these intervals to 750 µs and 1.75 ms, so the timer
+//! ticks every 250 µs instead. Only frames with a valid CRC are delivered.
+//!
+//! A master sends requests with [`send_request`](Rtu::send_request) and polls for the response
+//! with [`read_response`](Rtu::read_response). A slave polls for requests with
+//! [`read_request`](Rtu::read_request) and answers with [`send_response`](Rtu::send_response).
+//! The `read_*` methods have to be polled at least every half character, or the silent intervals
+//! are measured longer than they are.
+
+use embedded_hal::serial;
+use embedded_hal::timer::CountDown;
+use nb::block;
+
+use super::config::{Config, StopBits, WordLength};
+use crate::time::{Hertz, MilliSeconds};
+use crate::timer::{CountDownTimer, Timer};
+
+/// Maximum length of an ADU: address, PDU and CRC
+pub const MAX_ADU_LEN: usize = 256;
+
+/// Maximum length of a PDU: function code and data
+pub const MAX_PDU_LEN: usize = MAX_ADU_LEN - 3;
+
+/// Address of the requests sent to every slave, which don't answer them
+pub const BROADCAST: u8 = 0;
+
+// Silent intervals, in timer ticks of half a character
+const T15: u32 = 3;
+const T35: u32 = 7;
+
+/// Modbus RTU error
+#[non_exhaustive]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+    /// Error reported by the USART while receiving a frame
+    Serial(super::Error),
+    /// More than 1.5 characters between two bytes of a frame
+    Gap,
+    /// The frame is longer than [`MAX_ADU_LEN`]
+    Overflow,
+    /// The frame is too short for an address, a function code and a CRC
+    TooShort,
+    /// The CRC of the frame is wrong
+    Crc,
+    /// The response comes from another slave than the one requested
+    Address,
+    /// No response arrived in time
+    Timeout,
+}
+
+impl From<super::Error> for Error {
+    fn from(error: super::Error) -> Self {
+        Error::Serial(error)
+    }
+}
+
+/// Computes the Modbus CRC-16 of `data`, sent low byte first
+pub fn crc16(data: &[u8]) -> u16 {
+    crc16_update(0xFFFF, data)
+}
+
+fn crc16_update(crc: u16, data: &[u8]) -> u16 {
+    data.iter().fold(crc, |crc, &byte| {
+        (0..8).fold(crc ^ u16::from(byte), |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Application data unit received, its CRC checked
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Adu<'a> {
+    /// Slave address
+    pub address: u8,
+    /// Function code and data
+    pub pdu: &'a [u8],
+}
+
+/// Modbus RTU master or slave on a serial interface
+pub struct Rtu<SERIAL, TIM> {
+    serial: SERIAL,
+    timer: CountDownTimer<TIM>,
+    tick: Hertz,
+    // Ticks since the last byte received or sent
+    ticks: u32,
+    // Length of a character, rounded up to a whole number of ticks
+    char_ticks: u32,
+    response_timeout: u32,
+    buffer: [u8; MAX_ADU_LEN],
+    len: usize,
+    // Set on the bytes of an invalid frame, until its end
+    fault: Option<Error>,
+    // Address of the last request sent
+    request: u8,
+}
+
+impl<SERIAL, TIM> Rtu<SERIAL, TIM>
+where
+    SERIAL: serial::Read<u8, Error = super::Error> + serial::Write<u8, Error = super::Error>,
+    CountDownTimer<TIM>: CountDown<Time = Hertz>,
+{
+    /// Runs Modbus RTU on `serial`, configured with `config`, using `timer` to measure the
+    /// silent intervals
+    ///
+    /// The bus has to be silent for 3.5 characters before the first frame is received. The
+    /// response timeout of a master is 1 second by default.
+    pub fn new(serial: SERIAL, timer: Timer<TIM>, config: &Config) -> Self {
+        let baudrate = config.baudrate.0;
+        // The parity bit is part of the word
+        let word_bits = match config.wordlength {
+            WordLength::DataBits8 => 8,
+            WordLength::DataBits9 => 9,
+        };
+        // Half stop bits rounded up
+        let stop_bits = match config.stopbits {
+            StopBits::STOP0P5 | StopBits::STOP1 => 1,
+            StopBits::STOP1P5 | StopBits::STOP2 => 2,
+        };
+        let char_bits = 1 + word_bits + stop_bits;
+        let (tick, char_ticks) = if baudrate > 19_200 {
+            (Hertz(4_000), (char_bits * 4_000 + baudrate - 1) / baudrate)
+        } else {
+            (Hertz(2 * baudrate / char_bits), 2)
+        };
+
+        let mut rtu = Self {
+            serial,
+            timer: timer.start_count_down(tick),
+            tick,
+            ticks: 0,
+            char_ticks,
+            response_timeout: 0,
+            buffer: [0; MAX_ADU_LEN],
+            len: 0,
+            fault: None,
+            request: BROADCAST,
+        };
+        rtu.set_response_timeout(MilliSeconds(1_000));
+        rtu
+    }
+
+    /// Sets how long a master waits for the first byte of a response
+    pub fn set_response_timeout(&mut self, timeout: MilliSeconds) {
+        self.response_timeout =
+            (u64::from(timeout.0) * u64::from(self.tick.0) / 1_000).max(u64::from(T35)) as u32;
+    }
+
+    /// Releases the serial interface and the timer
+    pub fn release(self) -> (SERIAL, CountDownTimer<TIM>) {
+        (self.serial, self.timer)
+    }
+
+    /// Master: sends a request with `pdu` to the slave at `address`
+    ///
+    /// Waits for 3.5 silent characters first. Requests sent to [`BROADCAST`] get no response.
+    ///
+    /// # Panics
+    ///
+    /// * When `pdu` is longer than [`MAX_PDU_LEN`].
+    pub fn send_request(&mut self, address: u8, pdu: &[u8]) -> Result<(), Error> {
+        self.send(address, pdu)?;
+        self.request = address;
+        Ok(())
+    }
+
+    /// Master: polls for the response to the last request
+    ///
+    /// Returns `Timeout` when the response doesn't start within the response timeout, as for
+    /// broadcast requests.
+    pub fn read_response(&mut self) -> nb::Result<Adu<'_>, Error> {
+        if self.ticks >= self.response_timeout && self.is_silent() {
+            return Err(nb::Error::Other(Error::Timeout));
+        }
+        let len = self.poll()?;
+        let request = self.request;
+        let adu = self.adu(len);
+        if adu.address == request {
+            Ok(adu)
+        } else {
+            Err(nb::Error::Other(Error::Address))
+        }
+    }
+
+    /// Slave: polls for a request sent to `address` or broadcast
+    ///
+    /// Requests sent to other slaves are skipped.
+    pub fn read_request(&mut self, address: u8) -> nb::Result<Adu<'_>, Error> {
+        let len = self.poll()?;
+        let to = self.buffer[0];
+        if to == address || to == BROADCAST {
+            Ok(self.adu(len))
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Slave: sends a response with `pdu` from `address`
+    ///
+    /// Waits for 3.5 silent characters first.
+    ///
+    /// # Panics
+    ///
+    /// * When `pdu` is longer than [`MAX_PDU_LEN`].
+    pub fn send_response(&mut self, address: u8, pdu: &[u8]) -> Result<(), Error> {
+        self.send(address, pdu)
+    }
+
+    fn send(&mut self, address: u8, pdu: &[u8]) -> Result<(), Error> {
+        assert!(
+            pdu.len() <= MAX_PDU_LEN,
+            "Modbus RTU frames carry up to 253 PDU bytes"
+        );
+
+        while self.ticks < T35 {
+            self.update();
+        }
+
+        let crc = crc16_update(crc16(&[address]), pdu);
+        let [crc_low, crc_high] = crc.to_le_bytes();
+        for &byte in [address].iter().chain(pdu).chain(&[crc_low, crc_high]) {
+            block!(serial::Write::write(&mut self.serial, byte))?;
+        }
+        block!(serial::Write::flush(&mut self.serial))?;
+
+        // Drop the echo of a transceiver that keeps its receiver enabled
+        while !matches!(
+            serial::Read::read(&mut self.serial),
+            Err(nb::Error::WouldBlock)
+        ) {}
+        self.restart();
+        Ok(())
+    }
+
+    /// Receives the pending bytes and counts the ticks since the last one
+    fn update(&mut self) {
+        loop {
+            match serial::Read::read(&mut self.serial) {
+                Ok(byte) => {
+                    // The ticks run from the end of the previous byte to the end of this one
+                    if self.is_silent() {
+                        if self.ticks < T35 {
+                            // The tail of a frame, or a frame following another too closely
+                            self.fault = Some(Error::Gap);
+                        }
+                    } else if self.ticks >= T15 + self.char_ticks {
+                        self.fault.get_or_insert(Error::Gap);
+                    }
+                    if self.len < MAX_ADU_LEN {
+                        self.buffer[self.len] = byte;
+                        self.len += 1;
+                    } else {
+                        self.fault.get_or_insert(Error::Overflow);
+                    }
+                }
+                Err(nb::Error::Other(error)) => {
+                    self.fault.get_or_insert(Error::Serial(error));
+                }
+                Err(nb::Error::WouldBlock) => break,
+            }
+            self.ticks = 0;
+            self.restart_timer();
+        }
+
+        if self.timer.wait().is_ok() {
+            self.ticks = self.ticks.saturating_add(1);
+        }
+    }
+
+    /// Polls for the end of a frame, returns the length of the frame once it is in the buffer
+    /// with a valid CRC
+    fn poll(&mut self) -> nb::Result<usize, Error> {
+        self.update();
+        if self.ticks < T35 || self.is_silent() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let len = self.len;
+        let fault = self.fault.take();
+        self.len = 0;
+        if let Some(error) = fault {
+            return Err(nb::Error::Other(error));
+        }
+        if len < 4 {
+            return Err(nb::Error::Other(Error::TooShort));
+        }
+        let (adu, crc) = self.buffer[..len].split_at(len - 2);
+        if crc16(adu).to_le_bytes() != [crc[0], crc[1]] {
+            return Err(nb::Error::Other(Error::Crc));
+        }
+        Ok(len)
+    }
+
+    /// Returns the frame of length `len` left in the buffer by `poll`
+    fn adu(&self, len: usize) -> Adu<'_> {
+        Adu {
+            address: self.buffer[0],
+            pdu: &self.buffer[1..len - 2],
+        }
+    }
+
+    fn is_silent(&self) -> bool {
+        self.len == 0 && self.fault.is_none()
+    }
+
+    fn restart(&mut self) {
+        self.len = 0;
+        self.fault = None;
+        self.ticks = 0;
+        self.restart_timer();
+    }
+
+    /// Starts a new tick
+    fn restart_timer(&mut self) {
+        self.timer.start(self.tick);
+        // The start doesn't clear an update left pending by the previous tick, which would count
+        // a tick right away. The counter just restarted, so no new update can be lost.
+        self.timer.wait().ok();
+    }
+}
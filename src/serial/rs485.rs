@@ -0,0 +1,153 @@
+//! RS-485 half duplex links with the driver enable controlled by a GPIO
+
+use core::convert::Infallible;
+
+use embedded_hal::blocking;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::serial;
+
+use super::{Error, Instance, Rx, Serial, Tx};
+use crate::rcc::Clocks;
+
+/// Serial interface driving an RS-485 transceiver
+///
+/// The USARTs of the F2 have no driver enable output, so the DE (and the inverted RE) input of
+/// the transceiver is driven by a GPIO, high while transmitting. DE is asserted before the first
+/// byte and released only once the last stop bit was sent, i.e. once TC is set, after
+/// [`flush`](serial::Write::flush). The receiver is disabled in between so the local echo and
+/// the line turnaround are never read.
+///
+/// The guard times around a transmission are given in bit times and converted with the baud rate
+/// programmed in the USART.
+pub struct Rs485<USART, PINS, DE, WORD = u8> {
+    serial: Serial<USART, PINS, WORD>,
+    de: DE,
+    // Core clock cycles per bit
+    bit_cycles: u32,
+    assertion_bits: u32,
+    deassertion_bits: u32,
+    transmitting: bool,
+}
+
+impl<USART, PINS, DE, WORD> Rs485<USART, PINS, DE, WORD>
+where
+    USART: Instance,
+    DE: OutputPin<Error = Infallible>,
+{
+    /// Wraps `serial` with `de` driving the DE/RE inputs of the transceiver
+    ///
+    /// The guard times default to one bit time before the first start bit and after the last
+    /// stop bit.
+    pub fn new(serial: Serial<USART, PINS, WORD>, mut de: DE, clocks: Clocks) -> Self {
+        de.set_low().ok();
+
+        // NOTE(unsafe) atomic reads with no side effects
+        let usart = unsafe { &*USART::ptr() };
+        let brr = usart.brr.read().bits();
+        // 16 x USARTDIV, with the fraction of the 8 times oversampling back in 4 bits
+        let (div16, oversampling) = if usart.cr1.read().over8().bit_is_set() {
+            ((brr & !0xF) | ((brr & 0x7) << 1), 8)
+        } else {
+            (brr, 16)
+        };
+        let pclk = USART::get_frequency(&clocks).0;
+        let bit_cycles =
+            u64::from(clocks.sysclk().0) * u64::from(div16) * oversampling / 16 / u64::from(pclk);
+
+        Self {
+            serial,
+            de,
+            bit_cycles: bit_cycles as u32,
+            assertion_bits: 1,
+            deassertion_bits: 1,
+            transmitting: false,
+        }
+    }
+
+    /// Sets the time DE is asserted before the first start bit and held after the last stop
+    /// bit, in bit times
+    pub fn guard_times(mut self, assertion_bits: u8, deassertion_bits: u8) -> Self {
+        self.assertion_bits = assertion_bits.into();
+        self.deassertion_bits = deassertion_bits.into();
+        self
+    }
+
+    /// Returns true while the driver is enabled
+    pub fn is_transmitting(&self) -> bool {
+        self.transmitting
+    }
+
+    /// Releases the serial interface and the DE pin, stopping a transmission in progress
+    pub fn release(mut self) -> (Serial<USART, PINS, WORD>, DE) {
+        if self.transmitting {
+            self.end_transmission();
+        }
+        (self.serial, self.de)
+    }
+
+    fn start_transmission(&mut self) {
+        unsafe { (*USART::ptr()).cr1.modify(|_, w| w.re().clear_bit()) };
+        self.de.set_high().ok();
+        cortex_m::asm::delay(self.assertion_bits * self.bit_cycles);
+        self.transmitting = true;
+    }
+
+    fn end_transmission(&mut self) {
+        cortex_m::asm::delay(self.deassertion_bits * self.bit_cycles);
+        self.de.set_low().ok();
+        unsafe { (*USART::ptr()).cr1.modify(|_, w| w.re().set_bit()) };
+        self.transmitting = false;
+    }
+}
+
+impl<USART, PINS, DE, WORD> serial::Write<WORD> for Rs485<USART, PINS, DE, WORD>
+where
+    USART: Instance,
+    DE: OutputPin<Error = Infallible>,
+    Tx<USART, WORD>: serial::Write<WORD, Error = Error>,
+{
+    type Error = Error;
+
+    fn write(&mut self, word: WORD) -> nb::Result<(), Error> {
+        if !self.transmitting {
+            // Wait for room before taking the line, the data register may still be full from
+            // before the wrapper was created
+            if !self.serial.is_tx_empty() {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.start_transmission();
+        }
+        serial::Write::write(&mut self.serial, word)
+    }
+
+    /// Waits for the end of the last stop bit, then releases the line
+    fn flush(&mut self) -> nb::Result<(), Error> {
+        if !self.transmitting {
+            return Ok(());
+        }
+        serial::Write::flush(&mut self.serial)?;
+        self.end_transmission();
+        Ok(())
+    }
+}
+
+impl<USART, PINS, DE, WORD> serial::Read<WORD> for Rs485<USART, PINS, DE, WORD>
+where
+    USART: Instance,
+    Rx<USART, WORD>: serial::Read<WORD, Error = Error>,
+{
+    type Error = Error;
+
+    /// Reads a word, the receiver is off while transmitting
+    fn read(&mut self) -> nb::Result<WORD, Error> {
+        serial::Read::read(&mut self.serial)
+    }
+}
+
+impl<USART, PINS, DE, WORD> blocking::serial::write::Default<WORD> for Rs485<USART, PINS, DE, WORD>
+where
+    USART: Instance,
+    DE: OutputPin<Error = Infallible>,
+    Tx<USART, WORD>: serial::Write<WORD, Error = Error>,
+{
+}
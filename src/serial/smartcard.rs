@@ -0,0 +1,455 @@
+//! ISO 7816-3 smartcards using the T=0 protocol
+//!
+//! The card I/O line is connected to the TX pin of a USART, in open drain mode, and the card
+//! clock to its CK pin. The card reset is a GPIO. Parity errors are signalled in both directions
+//! with a NACK: the hardware sends one when a received byte is wrong and the card repeats it,
+//! bytes the card rejects are repeated by the driver.
+//!
+//! Only the direct convention is supported, the USART can't invert the data of inverse
+//! convention cards.
+
+use core::convert::Infallible;
+use core::iter;
+
+use embedded_hal::digital::v2::OutputPin;
+
+use super::config::InvalidConfig;
+use super::{PinCk, PinTx, UsartInstance};
+use crate::gpio::{Const, SetAlternate, SetAlternateOD};
+use crate::pac::RCC;
+use crate::rcc::Clocks;
+use crate::time::{Hertz, U32Ext};
+
+/// Maximum length of an answer to reset
+pub const MAX_ATR_LEN: usize = 33;
+
+// Card clock cycles the reset is held for, 400 at least
+const RESET_CLOCKS: u32 = 40_000;
+// The answer to reset starts at most 40000 card clock cycles after the reset is released
+const ATR_START_CLOCKS: u32 = 40_000;
+// Default work waiting time 960 x WI, with WI = 10, in etu
+const WORK_WAITING_TIME: u32 = 9600;
+
+/// Smartcard configuration
+pub struct Config {
+    /// Highest card clock frequency, the clock is the APB clock divided by an even number
+    pub clock: Hertz,
+    /// Card clock cycles per etu, F / D. 372 until the card agrees to something else.
+    pub etu: u16,
+    /// Guard time in etu, 12 plus the extra guard time N given by the card
+    pub guard_time: u8,
+    /// Number of times a byte rejected by the card is repeated
+    pub retries: u8,
+}
+
+impl Config {
+    pub fn clock(mut self, clock: Hertz) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn etu(mut self, etu: u16) -> Self {
+        self.etu = etu;
+        self
+    }
+
+    pub fn guard_time(mut self, guard_time: u8) -> Self {
+        self.guard_time = guard_time;
+        self
+    }
+
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            clock: 4_000_000.hz(),
+            etu: 372,
+            guard_time: 16,
+            retries: 3,
+        }
+    }
+}
+
+/// Smartcard error
+#[non_exhaustive]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+    /// The card did not answer within the waiting time
+    Timeout,
+    /// The card kept rejecting a byte after all the retries
+    Nack,
+    /// A received byte was lost
+    Overrun,
+    /// The answer to reset is malformed or uses the inverse convention
+    Atr,
+    /// The card sent an unexpected procedure byte
+    Protocol,
+    /// The command is not a valid short APDU
+    InvalidApdu,
+    /// The answer does not fit in the buffer
+    BufferTooSmall,
+}
+
+/// Smartcard interface
+pub struct Smartcard<USART, PINS, RST> {
+    usart: USART,
+    pins: PINS,
+    rst: RST,
+    etu: u16,
+    retries: u8,
+    // Core clock cycles per etu
+    etu_cycles: u32,
+}
+
+impl<USART, IO, CK, RST, const IOA: u8, const CKA: u8> Smartcard<USART, (IO, CK), RST>
+where
+    USART: UsartInstance,
+    IO: PinTx<USART, A = Const<IOA>> + SetAlternateOD<IOA>,
+    CK: PinCk<USART, A = Const<CKA>> + SetAlternate<CKA>,
+    RST: OutputPin<Error = Infallible>,
+{
+    /// Sets up the USART in smartcard mode and starts the card clock, the card is held in reset
+    ///
+    /// The I/O line needs a pull-up. Returns `InvalidConfig` when the card clock or the etu can't
+    /// be reached from the APB clock.
+    pub fn new(
+        usart: USART,
+        mut pins: (IO, CK),
+        mut rst: RST,
+        config: Config,
+        clocks: Clocks,
+    ) -> Result<Self, InvalidConfig> {
+        use crate::pac::usart1::cr2::STOP_A;
+
+        rst.set_low().ok();
+
+        let pclk_freq = USART::get_frequency(&clocks).0;
+        if config.clock.0 == 0 || config.etu == 0 {
+            return Err(InvalidConfig);
+        }
+        // The card clock is the APB clock divided by 2 x PSC, and an etu lasts `etu` card clocks
+        let psc = (pclk_freq + 2 * config.clock.0 - 1) / (2 * config.clock.0);
+        let brr = 2 * psc * u32::from(config.etu);
+        if psc > 31 || brr > 0xFFFF {
+            return Err(InvalidConfig);
+        }
+        let etu_cycles = u64::from(clocks.sysclk().0) * u64::from(brr) / u64::from(pclk_freq);
+
+        unsafe {
+            // NOTE(unsafe) this reference will only be used for atomic writes with no side effects.
+            let rcc = &(*RCC::ptr());
+
+            // Enable clock.
+            USART::enable(rcc);
+            USART::reset(rcc);
+
+            let usart = &*USART::usart_ptr();
+            usart.brr.write(|w| w.bits(brr));
+            usart
+                .gtpr
+                .write(|w| w.psc().bits(psc as u8).gt().bits(config.guard_time));
+            // 1.5 stop bits in both directions, with the card clock on CK
+            usart
+                .cr2
+                .write(|w| w.stop().variant(STOP_A::STOP1P5).clken().set_bit());
+            usart.cr3.write(|w| w.scen().set_bit().nack().set_bit());
+            // 8 data bits and even parity
+            usart.cr1.write(|w| {
+                w.ue()
+                    .set_bit()
+                    .m()
+                    .set_bit()
+                    .pce()
+                    .set_bit()
+                    .ps()
+                    .clear_bit()
+                    .te()
+                    .set_bit()
+                    .re()
+                    .set_bit()
+            });
+        }
+
+        SetAlternateOD::set_alt_mode(&mut pins.0);
+        SetAlternate::set_alt_mode(&mut pins.1);
+
+        Ok(Smartcard {
+            usart,
+            pins,
+            rst,
+            etu: config.etu,
+            retries: config.retries,
+            etu_cycles: etu_cycles as u32,
+        })
+    }
+
+    pub fn release(mut self) -> (USART, (IO, CK), RST) {
+        SetAlternateOD::restore_mode(&mut self.pins.0);
+        SetAlternate::restore_mode(&mut self.pins.1);
+
+        (self.usart, self.pins, self.rst)
+    }
+}
+
+impl<USART, PINS, RST> Smartcard<USART, PINS, RST>
+where
+    USART: UsartInstance,
+    RST: OutputPin<Error = Infallible>,
+{
+    /// Resets the card and reads its answer to reset into `atr`, returning its length
+    ///
+    /// `atr` should be able to hold [`MAX_ATR_LEN`] bytes.
+    pub fn reset(&mut self, atr: &mut [u8]) -> Result<usize, Error> {
+        self.rst.set_low().ok();
+        cortex_m::asm::delay(self.clocks_to_etus(RESET_CLOCKS) * self.etu_cycles);
+        self.discard_rx();
+        self.rst.set_high().ok();
+
+        let mut len = 0;
+        // The first byte also needs the time to be received
+        let ts = self.read_into(atr, &mut len, self.clocks_to_etus(ATR_START_CLOCKS) + 12)?;
+        if ts != 0x3B {
+            return Err(Error::Atr);
+        }
+
+        let t0 = self.read_into(atr, &mut len, WORK_WAITING_TIME)?;
+        let mut indicator = t0 >> 4;
+        let mut tck = false;
+        loop {
+            // TAi, TBi and TCi
+            for _ in 0..(indicator & 0x7).count_ones() {
+                self.read_into(atr, &mut len, WORK_WAITING_TIME)?;
+            }
+            if indicator & 0x8 == 0 {
+                break;
+            }
+            let td = self.read_into(atr, &mut len, WORK_WAITING_TIME)?;
+            // TCK is present unless T=0 is the only protocol offered
+            tck |= td & 0x0F != 0;
+            indicator = td >> 4;
+        }
+        // Historical bytes
+        for _ in 0..(t0 & 0x0F) {
+            self.read_into(atr, &mut len, WORK_WAITING_TIME)?;
+        }
+        if tck {
+            let checksum = self.read_into(atr, &mut len, WORK_WAITING_TIME)?;
+            // The exclusive or of all the bytes from T0 to TCK is 0
+            if atr[1..len - 1].iter().fold(checksum, |x, byte| x ^ byte) != 0 {
+                return Err(Error::Atr);
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Sets the guard time in etu, usually 12 plus the extra guard time N from the ATR
+    pub fn set_guard_time(&mut self, etus: u8) {
+        unsafe { (*USART::usart_ptr()).gtpr.modify(|_, w| w.gt().bits(etus)) }
+    }
+
+    /// Exchanges a short command APDU with the card using the T=0 protocol
+    ///
+    /// The response data followed by SW1 and SW2 is written to `response`, and its length
+    /// returned. `61XX` statuses are followed by GET RESPONSE commands fetching the data, `6CXX`
+    /// statuses by the same command with the length given by the card.
+    pub fn transceive(&mut self, apdu: &[u8], response: &mut [u8]) -> Result<usize, Error> {
+        let (header, data, le) = parse_apdu(apdu)?;
+
+        let mut len = 0;
+        let (mut sw1, mut sw2) = if data.is_empty() {
+            self.tpdu(header, le as u8, &[], le, response, &mut len)?
+        } else {
+            self.tpdu(header, data.len() as u8, data, 0, response, &mut len)?
+        };
+        loop {
+            let status = match sw1 {
+                // Wrong length, the card gives the right one
+                0x6C if data.is_empty() => {
+                    len = 0;
+                    self.tpdu(header, sw2, &[], length(sw2), response, &mut len)?
+                }
+                // More response bytes available
+                0x61 => {
+                    let get_response = [header[0], 0xC0, 0x00, 0x00];
+                    self.tpdu(get_response, sw2, &[], length(sw2), response, &mut len)?
+                }
+                _ => break,
+            };
+            sw1 = status.0;
+            sw2 = status.1;
+        }
+
+        let status = response
+            .get_mut(len..len + 2)
+            .ok_or(Error::BufferTooSmall)?;
+        status.copy_from_slice(&[sw1, sw2]);
+        Ok(len + 2)
+    }
+
+    /// Sends a command header and follows the procedure bytes of the card until it returns SW1
+    /// and SW2
+    ///
+    /// Sends `data`, or receives `incoming` bytes into `response` after `len`.
+    fn tpdu(
+        &mut self,
+        header: [u8; 4],
+        p3: u8,
+        data: &[u8],
+        incoming: usize,
+        response: &mut [u8],
+        len: &mut usize,
+    ) -> Result<(u8, u8), Error> {
+        let ins = header[1];
+        for &byte in header.iter().chain(iter::once(&p3)) {
+            self.write_byte(byte)?;
+        }
+
+        let mut sent = 0;
+        let mut received = 0;
+        loop {
+            let procedure = self.read_byte(WORK_WAITING_TIME)?;
+            match procedure {
+                // NULL, the card needs more time
+                0x60 => {}
+                // All remaining bytes
+                _ if procedure == ins => {
+                    if sent < data.len() {
+                        self.turnaround();
+                    }
+                    while sent < data.len() {
+                        self.write_byte(data[sent])?;
+                        sent += 1;
+                    }
+                    while received < incoming {
+                        self.read_into(response, len, WORK_WAITING_TIME)?;
+                        received += 1;
+                    }
+                }
+                // Next byte only
+                _ if procedure == !ins => {
+                    if sent < data.len() {
+                        self.turnaround();
+                        self.write_byte(data[sent])?;
+                        sent += 1;
+                    } else if received < incoming {
+                        self.read_into(response, len, WORK_WAITING_TIME)?;
+                        received += 1;
+                    } else {
+                        return Err(Error::Protocol);
+                    }
+                }
+                0x61..=0x6F | 0x90..=0x9F => {
+                    let sw2 = self.read_byte(WORK_WAITING_TIME)?;
+                    return Ok((procedure, sw2));
+                }
+                _ => return Err(Error::Protocol),
+            }
+        }
+    }
+
+    /// Sends a byte, repeating it while the card signals a parity error
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        // NOTE(unsafe) the USART is owned by the driver
+        let usart = unsafe { &*USART::usart_ptr() };
+        for _ in 0..=self.retries {
+            while usart.sr.read().txe().bit_is_clear() {}
+            usart.dr.write(|w| w.dr().bits(u16::from(byte)));
+            // TC is set at the end of the guard time, after a possible NACK
+            while usart.sr.read().tc().bit_is_clear() {}
+            // The byte is received back on the I/O line, with a framing error if it was NACKed
+            let sr = usart.sr.read();
+            let _ = usart.dr.read();
+            if sr.fe().bit_is_clear() {
+                return Ok(());
+            }
+        }
+        Err(Error::Nack)
+    }
+
+    /// Waits up to `etus` for a byte, skipping bytes with a parity error that the card repeats
+    fn read_byte(&mut self, etus: u32) -> Result<u8, Error> {
+        // NOTE(unsafe) the USART is owned by the driver
+        let usart = unsafe { &*USART::usart_ptr() };
+        let mut budget = etus;
+        loop {
+            let sr = usart.sr.read();
+            if sr.rxne().bit_is_set() {
+                let byte = usart.dr.read().dr().bits() as u8;
+                if sr.ore().bit_is_set() {
+                    return Err(Error::Overrun);
+                }
+                if sr.pe().bit_is_clear() {
+                    return Ok(byte);
+                }
+                // The hardware sent a NACK, wait for the repetition
+                budget = etus;
+            } else if budget == 0 {
+                return Err(Error::Timeout);
+            } else {
+                budget -= 1;
+                cortex_m::asm::delay(self.etu_cycles);
+            }
+        }
+    }
+
+    fn read_into(&mut self, buf: &mut [u8], len: &mut usize, etus: u32) -> Result<u8, Error> {
+        let byte = self.read_byte(etus)?;
+        *buf.get_mut(*len).ok_or(Error::BufferTooSmall)? = byte;
+        *len += 1;
+        Ok(byte)
+    }
+
+    /// Waits so that 16 etu separate the start of a byte received from the start of the next
+    /// byte sent
+    fn turnaround(&self) {
+        cortex_m::asm::delay(6 * self.etu_cycles);
+    }
+
+    fn clocks_to_etus(&self, clocks: u32) -> u32 {
+        clocks / u32::from(self.etu) + 1
+    }
+
+    fn discard_rx(&mut self) {
+        // NOTE(unsafe) reading SR then DR clears RXNE and the error flags
+        unsafe {
+            let _ = (*USART::usart_ptr()).sr.read();
+            let _ = (*USART::usart_ptr()).dr.read();
+        }
+    }
+}
+
+/// Response length coded in P3, where 0 stands for 256
+fn length(p3: u8) -> usize {
+    if p3 == 0 {
+        256
+    } else {
+        usize::from(p3)
+    }
+}
+
+/// Splits a short command APDU into its header, command data and expected response length
+fn parse_apdu(apdu: &[u8]) -> Result<([u8; 4], &[u8], usize), Error> {
+    if apdu.len() < 4 {
+        return Err(Error::InvalidApdu);
+    }
+    let header = [apdu[0], apdu[1], apdu[2], apdu[3]];
+    match &apdu[4..] {
+        // Case 1, no data
+        [] => Ok((header, &[], 0)),
+        // Case 2, response data only
+        [le] => Ok((header, &[], length(*le))),
+        // Case 3, command data only
+        [lc, data @ ..] if *lc != 0 && data.len() == usize::from(*lc) => Ok((header, data, 0)),
+        // Case 4, command and response data
+        [lc, data @ .., le] if *lc != 0 && data.len() == usize::from(*lc) => {
+            Ok((header, data, length(*le)))
+        }
+        _ => Err(Error::InvalidApdu),
+    }
+}
@@ -0,0 +1,199 @@
+//! SPI master on a USART in synchronous mode
+
+use embedded_hal::blocking;
+use embedded_hal::spi::{self, Mode, Phase, Polarity};
+
+use super::config::{Config, DmaConfig, InvalidConfig};
+use super::{Error, PinCk, PinRx, PinTx, Serial, UsartInstance};
+use crate::dma::traits::{DMASet, PeriAddress};
+use crate::dma::{MemoryToPeripheral, PeripheralToMemory};
+use crate::gpio::{Const, SetAlternate};
+use crate::rcc::Clocks;
+use crate::time::{Bps, Hertz};
+
+/// SPI master on a USART, with the clock on its CK pin
+///
+/// MOSI is the TX pin and MISO the RX pin. Frames are 8 bits, shifted LSB first as the USART
+/// has no MSB first mode, and the clock has a pulse for every bit including the last one. There
+/// is no slave select, use a GPIO.
+///
+/// DMA uses the requests of the USART, with the same streams and channels as [`Tx`](super::Tx)
+/// and [`Rx`](super::Rx).
+pub struct SyncSerial<USART, PINS> {
+    usart: USART,
+    pins: PINS,
+}
+
+impl<USART, TX, RX, CK, const TXA: u8, const RXA: u8, const CKA: u8> SyncSerial<USART, (TX, RX, CK)>
+where
+    USART: UsartInstance,
+    TX: PinTx<USART, A = Const<TXA>> + SetAlternate<TXA>,
+    RX: PinRx<USART, A = Const<RXA>> + SetAlternate<RXA>,
+    CK: PinCk<USART, A = Const<CKA>> + SetAlternate<CKA>,
+{
+    /// Creates a synchronous serial interface with the clock running at `freq`
+    ///
+    /// The clock can't be faster than a 8th of the APB clock, `InvalidConfig` is returned
+    /// otherwise.
+    pub fn new(
+        usart: USART,
+        pins: (TX, RX, CK),
+        mode: Mode,
+        freq: impl Into<Hertz>,
+        clocks: Clocks,
+    ) -> Result<Self, InvalidConfig> {
+        let (tx, rx, mut ck) = pins;
+        let config = Config::default().baudrate(Bps(freq.into().0));
+        let Serial {
+            usart,
+            pins: (tx, rx),
+            ..
+        } = Serial::<USART, (TX, RX), u8>::new(usart, (tx, rx), config, clocks)?;
+
+        unsafe {
+            let usart = &*USART::usart_ptr();
+            // The clock settings can't change while the transmitter is enabled
+            usart.cr1.modify(|_, w| w.ue().clear_bit());
+            usart.cr2.modify(|_, w| {
+                w.clken()
+                    .set_bit()
+                    .cpol()
+                    .bit(mode.polarity == Polarity::IdleHigh)
+                    .cpha()
+                    .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                    .lbcl()
+                    .set_bit()
+            });
+            usart.cr1.modify(|_, w| w.ue().set_bit());
+        }
+
+        ck.set_alt_mode();
+
+        Ok(SyncSerial {
+            usart,
+            pins: (tx, rx, ck),
+        })
+    }
+
+    pub fn release(mut self) -> (USART, (TX, RX, CK)) {
+        self.pins.0.restore_mode();
+        self.pins.1.restore_mode();
+        self.pins.2.restore_mode();
+
+        (self.usart, self.pins)
+    }
+}
+
+impl<USART, PINS> SyncSerial<USART, PINS>
+where
+    USART: UsartInstance,
+{
+    /// Enables the DMA requests of the directions used by DMA transfers
+    pub fn set_dma(&mut self, dma: DmaConfig) {
+        unsafe {
+            (*USART::ptr()).cr3.modify(|_, w| {
+                w.dmat()
+                    .bit(matches!(dma, DmaConfig::Tx | DmaConfig::TxRx))
+                    .dmar()
+                    .bit(matches!(dma, DmaConfig::Rx | DmaConfig::TxRx))
+            })
+        }
+    }
+}
+
+impl<USART, PINS> spi::FullDuplex<u8> for SyncSerial<USART, PINS>
+where
+    USART: UsartInstance,
+{
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        // NOTE(unsafe) atomic read with no side effects
+        let sr = unsafe { (*USART::ptr()).sr.read() };
+
+        if sr.ore().bit_is_set() {
+            // Clear the flag by reading DR
+            let _ = unsafe { (*USART::ptr()).dr.read() };
+            Err(nb::Error::Other(Error::Overrun))
+        } else if sr.rxne().bit_is_set() {
+            Ok(unsafe { (*USART::ptr()).dr.read().dr().bits() } as u8)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn send(&mut self, byte: u8) -> nb::Result<(), Error> {
+        // NOTE(unsafe) atomic read with no side effects
+        let sr = unsafe { (*USART::ptr()).sr.read() };
+
+        if sr.txe().bit_is_set() {
+            unsafe { (*USART::ptr()).dr.write(|w| w.dr().bits(u16::from(byte))) };
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<USART, PINS> blocking::spi::Transfer<u8> for SyncSerial<USART, PINS>
+where
+    USART: UsartInstance,
+{
+    type Error = Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        use spi::FullDuplex;
+
+        for word in words.iter_mut() {
+            nb::block!(self.send(*word))?;
+            *word = nb::block!(self.read())?;
+        }
+
+        Ok(words)
+    }
+}
+
+impl<USART, PINS> blocking::spi::Write<u8> for SyncSerial<USART, PINS>
+where
+    USART: UsartInstance,
+{
+    type Error = Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        use spi::FullDuplex;
+
+        for word in words {
+            nb::block!(self.send(*word))?;
+            nb::block!(self.read())?;
+        }
+
+        Ok(())
+    }
+}
+
+unsafe impl<USART, PINS> PeriAddress for SyncSerial<USART, PINS>
+where
+    USART: UsartInstance,
+{
+    #[inline(always)]
+    fn address(&self) -> u32 {
+        &(unsafe { &(*USART::ptr()) }.dr) as *const _ as u32
+    }
+
+    type MemSize = u8;
+}
+
+// The streams and channels of the USART requests, as mapped for `Tx` and `Rx`
+unsafe impl<USART, PINS, STREAM, const CHANNEL: u8> DMASet<STREAM, MemoryToPeripheral, CHANNEL>
+    for SyncSerial<USART, PINS>
+where
+    super::Tx<USART>: DMASet<STREAM, MemoryToPeripheral, CHANNEL>,
+{
+}
+
+unsafe impl<USART, PINS, STREAM, const CHANNEL: u8> DMASet<STREAM, PeripheralToMemory, CHANNEL>
+    for SyncSerial<USART, PINS>
+where
+    super::Rx<USART>: DMASet<STREAM, PeripheralToMemory, CHANNEL>,
+{
+}
@@ -0,0 +1,212 @@
+//! `embedded-io-async` traits, driven by the UART/USART interrupt
+//!
+//! This module is only available if the `async` feature is enabled.
+//!
+//! A waiting read enables the RXNE interrupt, a waiting write the TXE interrupt and a waiting
+//! flush the TC interrupt. The interrupt handler disables them again and wakes the task waiting
+//! on the reception or the transmission, leaving the flags and the data register to the future.
+//! The interrupt of the UART/USART has to be unmasked in the NVIC, and can't be shared with
+//! [`BufferedSerial`](super::BufferedSerial) or [`Rx::listen`] / [`Tx::listen`].
+//!
+//! With the `usart-handlers` feature the HAL defines the `USART1` to `USART6` interrupt handlers.
+//! Otherwise [`on_interrupt`] has to be called from them.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use atomic_waker::AtomicWaker;
+use embedded_hal::serial;
+use embedded_io_async::{Read, Write};
+
+use super::{Error, Instance, Rx, Serial, Tx};
+
+/// Tasks waiting on a UART/USART
+#[doc(hidden)]
+pub struct Wakers {
+    rx: AtomicWaker,
+    tx: AtomicWaker,
+}
+
+impl Wakers {
+    pub(super) const fn new() -> Self {
+        Self {
+            rx: AtomicWaker::new(),
+            tx: AtomicWaker::new(),
+        }
+    }
+}
+
+/// Handles the interrupt of `USART` and wakes the tasks waiting on it
+///
+/// Must be called from the UART/USART interrupt handler unless the `usart-handlers` feature is
+/// enabled.
+pub fn on_interrupt<USART: Instance>() {
+    let (rx, tx) = cortex_m::interrupt::free(|_| {
+        // NOTE(unsafe) the interrupt enables are only modified inside critical sections
+        let usart = unsafe { &*USART::ptr() };
+        let sr = usart.sr.read();
+        let cr1 = usart.cr1.read();
+        let rx = cr1.rxneie().bit_is_set() && (sr.rxne().bit_is_set() || sr.ore().bit_is_set());
+        let tx = (cr1.txeie().bit_is_set() && sr.txe().bit_is_set())
+            || (cr1.tcie().bit_is_set() && sr.tc().bit_is_set());
+        usart.cr1.modify(|_, w| {
+            if rx {
+                w.rxneie().clear_bit();
+            }
+            if tx {
+                w.txeie().clear_bit().tcie().clear_bit();
+            }
+            w
+        });
+        (rx, tx)
+    });
+
+    if rx {
+        USART::wakers().rx.wake();
+    }
+    if tx {
+        USART::wakers().tx.wake();
+    }
+}
+
+impl<USART> Rx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Waits for a byte or a receive error
+    pub async fn read_async(&mut self) -> Result<u8, Error> {
+        poll_fn(|cx| {
+            USART::wakers().rx.register(cx.waker());
+            match serial::Read::read(self) {
+                Ok(byte) => Poll::Ready(Ok(byte)),
+                Err(nb::Error::Other(error)) => Poll::Ready(Err(error)),
+                Err(nb::Error::WouldBlock) => {
+                    // A byte received since the read raises the interrupt right away
+                    cortex_m::interrupt::free(|_| unsafe {
+                        (*USART::ptr()).cr1.modify(|_, w| w.rxneie().set_bit())
+                    });
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl<USART> Tx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Waits until the transmitter accepts a byte
+    async fn wait_tx_empty(&mut self) {
+        poll_fn(|cx| {
+            USART::wakers().tx.register(cx.waker());
+            if self.is_tx_empty() {
+                Poll::Ready(())
+            } else {
+                cortex_m::interrupt::free(|_| unsafe {
+                    (*USART::ptr()).cr1.modify(|_, w| w.txeie().set_bit())
+                });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Waits until the last byte written is sent
+    pub async fn flush_async(&mut self) {
+        poll_fn(|cx| {
+            USART::wakers().tx.register(cx.waker());
+            // NOTE(unsafe) atomic read with no side effects
+            if unsafe { (*USART::ptr()).sr.read().tc().bit_is_set() } {
+                Poll::Ready(())
+            } else {
+                cortex_m::interrupt::free(|_| unsafe {
+                    (*USART::ptr()).cr1.modify(|_, w| w.tcie().set_bit())
+                });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<USART> Read for Rx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Waits for a byte, then reads the bytes already received up to the length of `buf`
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let first = self.read_async().await?;
+        self.read_received(first, buf)
+    }
+}
+
+impl<USART> Write for Tx<USART, u8>
+where
+    USART: Instance,
+{
+    /// Waits until the transmitter accepts a byte, then sends as many bytes as it accepts
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.wait_tx_empty().await;
+        Ok(self.write_accepted(buf))
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        self.flush_async().await;
+        Ok(())
+    }
+}
+
+impl<USART, PINS> Read for Serial<USART, PINS, u8>
+where
+    USART: Instance,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Read::read(&mut self.rx, buf).await
+    }
+}
+
+impl<USART, PINS> Write for Serial<USART, PINS, u8>
+where
+    USART: Instance,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(&mut self.tx, buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        Write::flush(&mut self.tx).await
+    }
+}
+
+#[cfg(feature = "usart-handlers")]
+mod handlers {
+    use crate::pac::{self, interrupt};
+
+    macro_rules! usart_handlers {
+        ($($name:ident: $usart:ty;)+) => {
+            $(
+                #[interrupt]
+                fn $name() {
+                    super::on_interrupt::<$usart>();
+                }
+            )+
+        };
+    }
+
+    usart_handlers!(
+        USART1: pac::USART1;
+        USART2: pac::USART2;
+        USART3: pac::USART3;
+        UART4: pac::UART4;
+        UART5: pac::UART5;
+        USART6: pac::USART6;
+    );
+}
@@ -1,3 +1,4 @@
+use core::marker::PhantomData;
 use core::ops::Deref;
 use core::ptr;
 
@@ -5,6 +6,8 @@ use crate::gpio::{Const, NoPin, SetAlternate};
 use embedded_hal::spi;
 pub use embedded_hal::spi::{Mode, Phase, Polarity};
 
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
+use crate::gpio::gpioi;
 use crate::gpio::{gpioa, gpiob, gpioc};
 
 use crate::pac::{spi1, RCC, SPI1, SPI2, SPI3};
@@ -23,6 +26,8 @@ pub enum Error {
     ModeFault,
     /// CRC error
     Crc,
+    /// The master clocked a byte the slave had no data loaded for
+    Underrun,
 }
 
 pub trait Pins<SPI> {}
@@ -35,6 +40,9 @@ pub trait PinMiso<SPI> {
 pub trait PinMosi<SPI> {
     type A;
 }
+pub trait PinNss<SPI> {
+    type A;
+}
 
 impl<SPI, SCK, MISO, MOSI> Pins<SPI> for (SCK, MISO, MOSI)
 where
@@ -124,6 +132,20 @@ pins! {
         ]
 }
 
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
+pins! {
+    SPI2:
+        SCK: [
+            gpioi::PI1<5>
+        ]
+        MISO: [
+            gpioi::PI2<5>
+        ]
+        MOSI: [
+            gpioi::PI3<5>
+        ]
+}
+
 pins! {
     SPI3:
         SCK: [
@@ -140,6 +162,29 @@ pins! {
         ]
 }
 
+macro_rules! nss_pins {
+    ($($SPIX:ty: [$($gpio:ident::$PX:ident<$A:literal>),*])+) => {
+        $(
+            $(
+                impl<MODE> PinNss<$SPIX> for $gpio::$PX<MODE> {
+                    type A = Const<$A>;
+                }
+            )*
+        )+
+    }
+}
+
+nss_pins! {
+    SPI1: [gpioa::PA4<5>, gpioa::PA15<5>]
+    SPI2: [gpiob::PB9<5>, gpiob::PB12<5>]
+    SPI3: [gpioa::PA4<6>, gpioa::PA15<6>]
+}
+
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
+nss_pins! {
+    SPI2: [gpioi::PI0<5>]
+}
+
 /// Interrupt events
 pub enum Event {
     /// New data has been received
@@ -155,11 +200,17 @@ pub struct TransferModeNormal;
 /// BIDI mode - use TX pin as RX then spi receive data
 pub struct TransferModeBidi;
 
+/// SPI master
+///
+/// Frames are 8 bits by default. [`into_u16`](Spi::into_u16) switches to 16 bit frames, turning
+/// the `Spi<_, _, _, u8>` into a `Spi<_, _, _, u16>` that implements the embedded-hal traits and
+/// the DMA transfers with `u16` words.
 #[derive(Debug)]
-pub struct Spi<SPI, PINS, TRANSFER_MODE> {
+pub struct Spi<SPI, PINS, TRANSFER_MODE, WORD = u8> {
     spi: SPI,
     pins: PINS,
     transfer_mode: TRANSFER_MODE,
+    _word: PhantomData<WORD>,
 }
 
 // Implemented by all SPI instances
@@ -175,6 +226,11 @@ impl Instance for SPI2 {}
 #[cfg(feature = "spi3")]
 impl Instance for SPI3 {}
 
+mod dma;
+pub use dma::DmaTransfer;
+mod slave;
+pub use slave::{SlaveDmaTransfer, SpiSlave};
+
 impl<SPI, SCK, MISO, MOSI, const SCKA: u8, const MISOA: u8, const MOSIA: u8>
     Spi<SPI, (SCK, MISO, MOSI), TransferModeNormal>
 where
@@ -205,12 +261,18 @@ where
             spi,
             pins,
             transfer_mode: TransferModeNormal,
+            _word: PhantomData,
         }
         .pre_init(mode, freq.into(), SPI::get_frequency(&clocks))
         .init()
     }
+}
 
-    pub fn to_bidi_transfer_mode(self) -> Spi<SPI, (SCK, MISO, MOSI), TransferModeBidi> {
+impl<SPI, PINS, WORD> Spi<SPI, PINS, TransferModeNormal, WORD>
+where
+    SPI: Instance,
+{
+    pub fn to_bidi_transfer_mode(self) -> Spi<SPI, PINS, TransferModeBidi, WORD> {
         let mut dev_w_new_t_mode = self.into_mode(TransferModeBidi {});
         dev_w_new_t_mode.enable(false);
         dev_w_new_t_mode.init()
@@ -247,20 +309,35 @@ where
             spi,
             pins,
             transfer_mode: TransferModeBidi,
+            _word: PhantomData,
         }
         .pre_init(mode, freq.into(), SPI::get_frequency(&clocks))
         .init()
     }
+}
 
-    pub fn to_normal_transfer_mode(self) -> Spi<SPI, (SCK, MISO, MOSI), TransferModeNormal> {
+impl<SPI, PINS, WORD> Spi<SPI, PINS, TransferModeBidi, WORD>
+where
+    SPI: Instance,
+{
+    pub fn to_normal_transfer_mode(self) -> Spi<SPI, PINS, TransferModeNormal, WORD> {
         let mut dev_w_new_t_mode = self.into_mode(TransferModeNormal {});
         dev_w_new_t_mode.enable(false);
         dev_w_new_t_mode.init()
     }
 }
 
-impl<SPI, SCK, MISO, MOSI, TRANSFER_MODE, const SCKA: u8, const MISOA: u8, const MOSIA: u8>
-    Spi<SPI, (SCK, MISO, MOSI), TRANSFER_MODE>
+impl<
+        SPI,
+        SCK,
+        MISO,
+        MOSI,
+        TRANSFER_MODE,
+        WORD,
+        const SCKA: u8,
+        const MISOA: u8,
+        const MOSIA: u8,
+    > Spi<SPI, (SCK, MISO, MOSI), TRANSFER_MODE, WORD>
 where
     SPI: Instance,
     SCK: PinSck<SPI, A = Const<SCKA>> + SetAlternate<SCKA>,
@@ -276,7 +353,7 @@ where
     }
 }
 
-impl<SPI, PINS> Spi<SPI, PINS, TransferModeNormal>
+impl<SPI, PINS, WORD> Spi<SPI, PINS, TransferModeNormal, WORD>
 where
     SPI: Instance,
 {
@@ -296,7 +373,7 @@ where
     }
 }
 
-impl<SPI, PINS> Spi<SPI, PINS, TransferModeBidi>
+impl<SPI, PINS, WORD> Spi<SPI, PINS, TransferModeBidi, WORD>
 where
     SPI: Instance,
 {
@@ -320,24 +397,13 @@ impl<SPI, PINS, TRANSFER_MODE> Spi<SPI, PINS, TRANSFER_MODE>
 where
     SPI: Instance,
 {
-    /// Convert the spi to another transfer mode.
-    fn into_mode<TRANSFER_MODE2>(
-        self,
-        transfer_mode: TRANSFER_MODE2,
-    ) -> Spi<SPI, PINS, TRANSFER_MODE2> {
-        Spi {
-            spi: self.spi,
-            pins: self.pins,
-            transfer_mode,
-        }
-    }
-
-    /// Enable/disable spi
-    pub fn enable(&mut self, enable: bool) {
-        self.spi.cr1.modify(|_, w| {
-            // spe: enable the SPI bus
-            w.spe().bit(enable)
-        });
+    /// Switches to 16 bit frames
+    pub fn into_u16(mut self) -> Spi<SPI, PINS, TRANSFER_MODE, u16> {
+        // The frame format can only change while the SPI is disabled
+        self.enable(false);
+        self.spi.cr1.modify(|_, w| w.dff().set_bit());
+        self.enable(true);
+        self.into_word()
     }
 
     /// Pre initializing the SPI bus.
@@ -385,6 +451,55 @@ where
 
         self
     }
+}
+
+impl<SPI, PINS, TRANSFER_MODE> Spi<SPI, PINS, TRANSFER_MODE, u16>
+where
+    SPI: Instance,
+{
+    /// Switches back to 8 bit frames
+    pub fn into_u8(mut self) -> Spi<SPI, PINS, TRANSFER_MODE, u8> {
+        self.enable(false);
+        self.spi.cr1.modify(|_, w| w.dff().clear_bit());
+        self.enable(true);
+        self.into_word()
+    }
+}
+
+impl<SPI, PINS, TRANSFER_MODE, WORD> Spi<SPI, PINS, TRANSFER_MODE, WORD>
+where
+    SPI: Instance,
+{
+    /// Convert the spi to another transfer mode.
+    fn into_mode<TRANSFER_MODE2>(
+        self,
+        transfer_mode: TRANSFER_MODE2,
+    ) -> Spi<SPI, PINS, TRANSFER_MODE2, WORD> {
+        Spi {
+            spi: self.spi,
+            pins: self.pins,
+            transfer_mode,
+            _word: PhantomData,
+        }
+    }
+
+    /// Convert the spi to another word type, the frame format has to be set.
+    fn into_word<WORD2>(self) -> Spi<SPI, PINS, TRANSFER_MODE, WORD2> {
+        Spi {
+            spi: self.spi,
+            pins: self.pins,
+            transfer_mode: self.transfer_mode,
+            _word: PhantomData,
+        }
+    }
+
+    /// Enable/disable spi
+    pub fn enable(&mut self, enable: bool) {
+        self.spi.cr1.modify(|_, w| {
+            // spe: enable the SPI bus
+            w.spe().bit(enable)
+        });
+    }
 
     /// Enable interrupts for the given `event`:
     ///  - Received data ready to be read (RXNE)
@@ -435,7 +550,7 @@ where
     }
 
     #[inline(always)]
-    fn check_read(&mut self) -> nb::Result<u8, Error> {
+    fn check_read(&mut self) -> nb::Result<(), Error> {
         let sr = self.spi.sr.read();
 
         Err(if sr.ovr().bit_is_set() {
@@ -445,14 +560,14 @@ where
         } else if sr.crcerr().bit_is_set() {
             Error::Crc.into()
         } else if sr.rxne().bit_is_set() {
-            return Ok(self.read_u8());
+            return Ok(());
         } else {
             nb::Error::WouldBlock
         })
     }
 
     #[inline(always)]
-    fn check_send(&mut self, byte: u8) -> nb::Result<(), Error> {
+    fn check_send(&mut self) -> nb::Result<(), Error> {
         let sr = self.spi.sr.read();
 
         Err(if sr.ovr().bit_is_set() {
@@ -471,7 +586,6 @@ where
             });
             Error::Crc.into()
         } else if sr.txe().bit_is_set() {
-            self.send_u8(byte);
             return Ok(());
         } else {
             nb::Error::WouldBlock
@@ -487,26 +601,39 @@ where
     #[inline(always)]
     fn send_u8(&mut self, byte: u8) {
         // NOTE(write_volatile) see note above
-        unsafe { ptr::write_volatile(&self.spi.dr as *const _ as *mut u8, byte) }
+        unsafe { ptr::write_volatile(self.spi.dr.as_ptr() as *mut u8, byte) }
+    }
+
+    #[inline(always)]
+    fn read_u16(&mut self) -> u16 {
+        self.spi.dr.read().dr().bits()
+    }
+
+    #[inline(always)]
+    fn send_u16(&mut self, word: u16) {
+        self.spi.dr.write(|w| w.dr().bits(word))
     }
 }
 
-impl<SPI, PINS> spi::FullDuplex<u8> for Spi<SPI, PINS, TransferModeNormal>
+impl<SPI, PINS> spi::FullDuplex<u8> for Spi<SPI, PINS, TransferModeNormal, u8>
 where
     SPI: Instance,
 {
     type Error = Error;
 
     fn read(&mut self) -> nb::Result<u8, Error> {
-        self.check_read()
+        self.check_read()?;
+        Ok(self.read_u8())
     }
 
     fn send(&mut self, byte: u8) -> nb::Result<(), Error> {
-        self.check_send(byte)
+        self.check_send()?;
+        self.send_u8(byte);
+        Ok(())
     }
 }
 
-impl<SPI, PINS> spi::FullDuplex<u8> for Spi<SPI, PINS, TransferModeBidi>
+impl<SPI, PINS> spi::FullDuplex<u8> for Spi<SPI, PINS, TransferModeBidi, u8>
 where
     SPI: Instance,
 {
@@ -514,110 +641,143 @@ where
 
     fn read(&mut self) -> nb::Result<u8, Error> {
         self.spi.cr1.modify(|_, w| w.bidioe().clear_bit());
-        self.check_read()
+        self.check_read()?;
+        Ok(self.read_u8())
     }
 
     fn send(&mut self, byte: u8) -> nb::Result<(), Error> {
         self.spi.cr1.modify(|_, w| w.bidioe().set_bit());
-        self.check_send(byte)
+        self.check_send()?;
+        self.send_u8(byte);
+        Ok(())
     }
 }
 
-mod blocking {
-    use super::{Error, Instance, Spi, TransferModeBidi, TransferModeNormal};
-    use embedded_hal::blocking::spi::{Operation, Transactional, Transfer, Write, WriteIter};
-    use embedded_hal::spi::FullDuplex;
-
-    impl<SPI, PINS, TRANSFER_MODE> Transfer<u8> for Spi<SPI, PINS, TRANSFER_MODE>
-    where
-        Self: FullDuplex<u8, Error = Error>,
-        SPI: Instance,
-    {
-        type Error = Error;
-
-        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
-            for word in words.iter_mut() {
-                nb::block!(self.send(*word))?;
-                *word = nb::block!(self.read())?;
-            }
+impl<SPI, PINS> spi::FullDuplex<u16> for Spi<SPI, PINS, TransferModeNormal, u16>
+where
+    SPI: Instance,
+{
+    type Error = Error;
 
-            Ok(words)
-        }
+    fn read(&mut self) -> nb::Result<u16, Error> {
+        self.check_read()?;
+        Ok(self.read_u16())
     }
 
-    impl<SPI, PINS> Write<u8> for Spi<SPI, PINS, TransferModeNormal>
-    where
-        Self: FullDuplex<u8, Error = Error>,
-        SPI: Instance,
-    {
-        type Error = Error;
-
-        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
-            for word in words {
-                nb::block!(self.send(*word))?;
-                nb::block!(self.read())?;
-            }
-
-            Ok(())
-        }
+    fn send(&mut self, word: u16) -> nb::Result<(), Error> {
+        self.check_send()?;
+        self.send_u16(word);
+        Ok(())
     }
+}
 
-    impl<SPI, PINS> Write<u8> for Spi<SPI, PINS, TransferModeBidi>
-    where
-        Self: FullDuplex<u8, Error = Error>,
-        SPI: Instance,
-    {
-        type Error = Error;
+impl<SPI, PINS> spi::FullDuplex<u16> for Spi<SPI, PINS, TransferModeBidi, u16>
+where
+    SPI: Instance,
+{
+    type Error = Error;
 
-        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
-            for word in words {
-                nb::block!(self.send(*word))?;
-            }
+    fn read(&mut self) -> nb::Result<u16, Error> {
+        self.spi.cr1.modify(|_, w| w.bidioe().clear_bit());
+        self.check_read()?;
+        Ok(self.read_u16())
+    }
 
-            Ok(())
-        }
+    fn send(&mut self, word: u16) -> nb::Result<(), Error> {
+        self.spi.cr1.modify(|_, w| w.bidioe().set_bit());
+        self.check_send()?;
+        self.send_u16(word);
+        Ok(())
     }
+}
 
-    impl<SPI, PINS> WriteIter<u8> for Spi<SPI, PINS, TransferModeNormal>
-    where
-        Self: FullDuplex<u8, Error = Error>,
-        SPI: Instance,
-    {
-        type Error = Error;
+mod blocking {
+    use super::{Error, Instance, Spi, TransferModeBidi, TransferModeNormal};
+    use embedded_hal::blocking::spi::{
+        transfer, write, Operation, Transactional, Transfer, Write, WriteIter,
+    };
+    use embedded_hal::spi::FullDuplex;
 
-        fn write_iter<WI>(&mut self, words: WI) -> Result<(), Self::Error>
-        where
-            WI: IntoIterator<Item = u8>,
-        {
-            for word in words.into_iter() {
-                nb::block!(self.send(word))?;
-                nb::block!(self.read())?;
-            }
+    macro_rules! blocking {
+        ($($W:ty),+) => {
+            $(
+                impl<SPI, PINS, TRANSFER_MODE> transfer::Default<$W>
+                    for Spi<SPI, PINS, TRANSFER_MODE, $W>
+                where
+                    Self: FullDuplex<$W, Error = Error>,
+                    SPI: Instance,
+                {
+                }
 
-            Ok(())
-        }
-    }
+                impl<SPI, PINS> write::Default<$W> for Spi<SPI, PINS, TransferModeNormal, $W>
+                where
+                    Self: FullDuplex<$W, Error = Error>,
+                    SPI: Instance,
+                {
+                }
 
-    impl<SPI, PINS> WriteIter<u8> for Spi<SPI, PINS, TransferModeBidi>
-    where
-        Self: FullDuplex<u8, Error = Error>,
-        SPI: Instance,
-    {
-        type Error = Error;
+                // Nothing is received in BIDI mode while sending
+                impl<SPI, PINS> Write<$W> for Spi<SPI, PINS, TransferModeBidi, $W>
+                where
+                    Self: FullDuplex<$W, Error = Error>,
+                    SPI: Instance,
+                {
+                    type Error = Error;
+
+                    fn write(&mut self, words: &[$W]) -> Result<(), Self::Error> {
+                        for word in words {
+                            nb::block!(self.send(*word))?;
+                        }
+
+                        Ok(())
+                    }
+                }
 
-        fn write_iter<WI>(&mut self, words: WI) -> Result<(), Self::Error>
-        where
-            WI: IntoIterator<Item = u8>,
-        {
-            for word in words.into_iter() {
-                nb::block!(self.send(word))?;
-            }
+                impl<SPI, PINS> WriteIter<$W> for Spi<SPI, PINS, TransferModeNormal, $W>
+                where
+                    Self: FullDuplex<$W, Error = Error>,
+                    SPI: Instance,
+                {
+                    type Error = Error;
+
+                    fn write_iter<WI>(&mut self, words: WI) -> Result<(), Self::Error>
+                    where
+                        WI: IntoIterator<Item = $W>,
+                    {
+                        for word in words.into_iter() {
+                            nb::block!(self.send(word))?;
+                            nb::block!(self.read())?;
+                        }
+
+                        Ok(())
+                    }
+                }
 
-            Ok(())
-        }
+                impl<SPI, PINS> WriteIter<$W> for Spi<SPI, PINS, TransferModeBidi, $W>
+                where
+                    Self: FullDuplex<$W, Error = Error>,
+                    SPI: Instance,
+                {
+                    type Error = Error;
+
+                    fn write_iter<WI>(&mut self, words: WI) -> Result<(), Self::Error>
+                    where
+                        WI: IntoIterator<Item = $W>,
+                    {
+                        for word in words.into_iter() {
+                            nb::block!(self.send(word))?;
+                        }
+
+                        Ok(())
+                    }
+                }
+            )+
+        };
     }
 
-    impl<SPI, PINS, TRANSFER_MODE, W: 'static> Transactional<W> for Spi<SPI, PINS, TRANSFER_MODE>
+    blocking!(u8, u16);
+
+    impl<SPI, PINS, TRANSFER_MODE, W: 'static> Transactional<W> for Spi<SPI, PINS, TRANSFER_MODE, W>
     where
         Self: Write<W, Error = Error> + Transfer<W, Error = Error>,
     {
@@ -0,0 +1,352 @@
+//! Full duplex SPI transfers using a pair of DMA streams
+//!
+//! The words of the buffers are the frames of the SPI: `u8` by default, `u16` after
+//! [`Spi::into_u16`].
+
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
+
+use super::{Instance, Spi, TransferModeNormal};
+use crate::dma::traits::{Channel, DMASet, Direction, PeriAddress, Stream};
+use crate::dma::{ChannelX, MemoryToPeripheral, PeripheralToMemory};
+
+pub(crate) mod sealed {
+    /// A frame of the SPI as stored in a DMA buffer, `u8` or `u16`
+    pub trait Word {
+        /// DMA memory and peripheral size: 0 for bytes, 1 for half words
+        const SIZE: u8;
+    }
+    impl Word for u8 {
+        const SIZE: u8 = 0;
+    }
+    impl Word for u16 {
+        const SIZE: u8 = 1;
+    }
+}
+use sealed::Word;
+
+// The DMA needs a fixed address to send the filler word of a read from and to discard the words
+// received during a write to. Both live in RAM so that DMA1 can reach them, and are wide enough
+// for 16 bit frames.
+static mut DUMMY_TX: u16 = 0xFFFF;
+static mut DUMMY_RX: u16 = 0;
+
+/// A full duplex SPI transfer running on a TX and a RX DMA stream
+///
+/// Created by [`Spi::dma_transfer`], [`Spi::dma_write`] or [`Spi::dma_read`]. Dropping the
+/// transfer stops both streams.
+pub struct DmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF, WORD = u8>
+where
+    SPI: Instance,
+    TXSTREAM: Stream,
+    RXSTREAM: Stream,
+{
+    spi: Spi<SPI, PINS, TransferModeNormal, WORD>,
+    tx_stream: TXSTREAM,
+    rx_stream: RXSTREAM,
+    tx_buf: TXBUF,
+    rx_buf: RXBUF,
+}
+
+impl<SPI, PINS, WORD> Spi<SPI, PINS, TransferModeNormal, WORD>
+where
+    SPI: Instance + PeriAddress,
+    WORD: Word,
+{
+    /// Sends `tx_buf` while receiving into `rx_buf`, both buffers must have the same length
+    ///
+    /// # Panics
+    ///
+    /// * When the buffer lengths differ.
+    /// * When the buffers are empty or longer than 65535 words.
+    pub fn dma_transfer<TXSTREAM, RXSTREAM, TXBUF, RXBUF, const TXCH: u8, const RXCH: u8>(
+        self,
+        tx_stream: TXSTREAM,
+        rx_stream: RXSTREAM,
+        tx_buf: TXBUF,
+        mut rx_buf: RXBUF,
+    ) -> DmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF, WORD>
+    where
+        TXSTREAM: Stream,
+        RXSTREAM: Stream,
+        ChannelX<TXCH>: Channel,
+        ChannelX<RXCH>: Channel,
+        SPI:
+            DMASet<TXSTREAM, MemoryToPeripheral, TXCH> + DMASet<RXSTREAM, PeripheralToMemory, RXCH>,
+        TXBUF: StaticReadBuffer<Word = WORD>,
+        RXBUF: StaticWriteBuffer<Word = WORD>,
+    {
+        // NOTE(unsafe) We now own the buffers and won't touch them until the transfer is over
+        let (tx_ptr, tx_len) = unsafe { tx_buf.read_buffer() };
+        let (rx_ptr, rx_len) = unsafe { rx_buf.write_buffer() };
+        assert_eq!(tx_len, rx_len, "Buffer lengths differ");
+        let len = transfer_len(tx_len);
+
+        DmaTransfer::start::<TXCH, RXCH>(
+            self,
+            tx_stream,
+            rx_stream,
+            tx_buf,
+            rx_buf,
+            (tx_ptr as u32, true),
+            (rx_ptr as u32, true),
+            len,
+        )
+    }
+
+    /// Sends `tx_buf`, the received words are discarded
+    ///
+    /// # Panics
+    ///
+    /// * When `tx_buf` is empty or longer than 65535 words.
+    pub fn dma_write<TXSTREAM, RXSTREAM, TXBUF, const TXCH: u8, const RXCH: u8>(
+        self,
+        tx_stream: TXSTREAM,
+        rx_stream: RXSTREAM,
+        tx_buf: TXBUF,
+    ) -> DmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, (), WORD>
+    where
+        TXSTREAM: Stream,
+        RXSTREAM: Stream,
+        ChannelX<TXCH>: Channel,
+        ChannelX<RXCH>: Channel,
+        SPI:
+            DMASet<TXSTREAM, MemoryToPeripheral, TXCH> + DMASet<RXSTREAM, PeripheralToMemory, RXCH>,
+        TXBUF: StaticReadBuffer<Word = WORD>,
+    {
+        // NOTE(unsafe) We now own the buffer and won't touch it until the transfer is over
+        let (tx_ptr, tx_len) = unsafe { tx_buf.read_buffer() };
+        let len = transfer_len(tx_len);
+        let dummy = unsafe { ptr::addr_of_mut!(DUMMY_RX) };
+
+        DmaTransfer::start::<TXCH, RXCH>(
+            self,
+            tx_stream,
+            rx_stream,
+            tx_buf,
+            (),
+            (tx_ptr as u32, true),
+            (dummy as u32, false),
+            len,
+        )
+    }
+
+    /// Receives into `rx_buf` while sending `0xFF` or `0xFFFF`
+    ///
+    /// # Panics
+    ///
+    /// * When `rx_buf` is empty or longer than 65535 words.
+    pub fn dma_read<TXSTREAM, RXSTREAM, RXBUF, const TXCH: u8, const RXCH: u8>(
+        self,
+        tx_stream: TXSTREAM,
+        rx_stream: RXSTREAM,
+        mut rx_buf: RXBUF,
+    ) -> DmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, (), RXBUF, WORD>
+    where
+        TXSTREAM: Stream,
+        RXSTREAM: Stream,
+        ChannelX<TXCH>: Channel,
+        ChannelX<RXCH>: Channel,
+        SPI:
+            DMASet<TXSTREAM, MemoryToPeripheral, TXCH> + DMASet<RXSTREAM, PeripheralToMemory, RXCH>,
+        RXBUF: StaticWriteBuffer<Word = WORD>,
+    {
+        // NOTE(unsafe) We now own the buffer and won't touch it until the transfer is over
+        let (rx_ptr, rx_len) = unsafe { rx_buf.write_buffer() };
+        let len = transfer_len(rx_len);
+        let dummy = unsafe { ptr::addr_of!(DUMMY_TX) };
+
+        DmaTransfer::start::<TXCH, RXCH>(
+            self,
+            tx_stream,
+            rx_stream,
+            (),
+            rx_buf,
+            (dummy as u32, false),
+            (rx_ptr as u32, true),
+            len,
+        )
+    }
+}
+
+impl<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF, WORD>
+    DmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF, WORD>
+where
+    SPI: Instance + PeriAddress,
+    TXSTREAM: Stream,
+    RXSTREAM: Stream,
+    WORD: Word,
+{
+    #[allow(clippy::too_many_arguments)]
+    fn start<const TXCH: u8, const RXCH: u8>(
+        spi: Spi<SPI, PINS, TransferModeNormal, WORD>,
+        mut tx_stream: TXSTREAM,
+        mut rx_stream: RXSTREAM,
+        tx_buf: TXBUF,
+        rx_buf: RXBUF,
+        // memory address and memory increment
+        tx: (u32, bool),
+        rx: (u32, bool),
+        len: u16,
+    ) -> Self
+    where
+        ChannelX<TXCH>: Channel,
+        ChannelX<RXCH>: Channel,
+    {
+        let address = spi.spi.address();
+        configure_stream::<_, _, WORD, RXCH>(&mut rx_stream, PeripheralToMemory, address, rx, len);
+        configure_stream::<_, _, WORD, TXCH>(&mut tx_stream, MemoryToPeripheral, address, tx, len);
+
+        // Drop a stale word so it doesn't end up at the start of the receive buffer
+        let _ = spi.spi.sr.read();
+        let _ = spi.spi.dr.read();
+
+        // "Preceding reads and writes cannot be moved past subsequent writes"
+        compiler_fence(Ordering::Release);
+
+        // Order from the reference manual: RX requests, both streams, then TX requests, so no
+        // received word can be missed
+        spi.spi.cr2.modify(|_, w| w.rxdmaen().set_bit());
+        unsafe {
+            rx_stream.enable();
+            tx_stream.enable();
+        }
+        spi.spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+
+        Self {
+            spi,
+            tx_stream,
+            rx_stream,
+            tx_buf,
+            rx_buf,
+        }
+    }
+
+    /// Returns true once the last word was received and the SPI is no longer busy
+    pub fn is_complete(&self) -> bool {
+        RXSTREAM::get_transfer_complete_flag() && self.spi.spi.sr.read().bsy().bit_is_clear()
+    }
+
+    /// Waits for the end of the transfer, then returns the SPI, the streams and the buffers
+    pub fn wait(
+        mut self,
+    ) -> (
+        Spi<SPI, PINS, TransferModeNormal, WORD>,
+        TXSTREAM,
+        RXSTREAM,
+        TXBUF,
+        RXBUF,
+    ) {
+        while !self.is_complete() {}
+        self.stop();
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads"
+        compiler_fence(Ordering::Acquire);
+
+        unsafe {
+            let spi = ptr::read(&self.spi);
+            let tx_stream = ptr::read(&self.tx_stream);
+            let rx_stream = ptr::read(&self.rx_stream);
+            let tx_buf = ptr::read(&self.tx_buf);
+            let rx_buf = ptr::read(&self.rx_buf);
+            mem::forget(self);
+            (spi, tx_stream, rx_stream, tx_buf, rx_buf)
+        }
+    }
+
+    fn stop(&mut self) {
+        self.spi
+            .spi
+            .cr2
+            .modify(|_, w| w.txdmaen().clear_bit().rxdmaen().clear_bit());
+        self.tx_stream.disable();
+        self.rx_stream.disable();
+        self.tx_stream.clear_interrupts();
+        self.rx_stream.clear_interrupts();
+    }
+}
+
+impl<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF, WORD> Drop
+    for DmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF, WORD>
+where
+    SPI: Instance,
+    TXSTREAM: Stream,
+    RXSTREAM: Stream,
+{
+    fn drop(&mut self) {
+        self.spi
+            .spi
+            .cr2
+            .modify(|_, w| w.txdmaen().clear_bit().rxdmaen().clear_bit());
+        self.tx_stream.disable();
+        self.rx_stream.disable();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Checks that a stream can transfer `len` words: NDTR holds up to 65535 and a stream started with
+/// 0 never completes
+pub(super) fn transfer_len(len: usize) -> u16 {
+    assert!(
+        0 < len && len <= u16::MAX as usize,
+        "DMA transfers carry 1 to 65535 words"
+    );
+    len as u16
+}
+
+/// Configures a stream for `WORD` transfers between the SPI data register and memory
+pub(super) fn configure_stream<STREAM, DIR, WORD, const CHANNEL: u8>(
+    stream: &mut STREAM,
+    direction: DIR,
+    peripheral_address: u32,
+    (memory_address, memory_increment): (u32, bool),
+    len: u16,
+) where
+    STREAM: Stream,
+    DIR: Direction,
+    WORD: Word,
+    ChannelX<CHANNEL>: Channel,
+{
+    stream.disable();
+    stream.clear_interrupts();
+    stream.set_channel::<CHANNEL>();
+    stream.set_direction(direction);
+    stream.set_peripheral_address(peripheral_address);
+    stream.set_memory_address(memory_address);
+    stream.set_number_of_transfers(len);
+    // NOTE(unsafe) The same size on both sides, matching the frame format of the SPI
+    unsafe {
+        stream.set_memory_size(WORD::SIZE);
+        stream.set_peripheral_size(WORD::SIZE);
+    }
+    stream.set_memory_increment(memory_increment);
+    stream.set_peripheral_increment(false);
+    stream.set_interrupts_enable(false, false, false, false);
+    stream.set_fifo_error_interrupt_enable(false);
+    stream.set_double_buffer(false);
+    stream.set_circular_mode(false);
+    stream.set_fifo_enable(false);
+}
+
+// The SPI in 16 bit frame format, for the `dma::Transfer` of the DMA module: its data register is
+// accessed by half words on the streams of the SPI.
+unsafe impl<SPI, PINS> PeriAddress for Spi<SPI, PINS, TransferModeNormal, u16>
+where
+    SPI: Instance + PeriAddress,
+{
+    type MemSize = u16;
+
+    fn address(&self) -> u32 {
+        self.spi.address()
+    }
+}
+
+unsafe impl<SPI, PINS, STREAM, DIR, const CHANNEL: u8> DMASet<STREAM, DIR, CHANNEL>
+    for Spi<SPI, PINS, TransferModeNormal, u16>
+where
+    SPI: DMASet<STREAM, DIR, CHANNEL>,
+{
+}
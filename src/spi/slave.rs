@@ -0,0 +1,458 @@
+//! SPI slave with hardware slave select
+
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
+use embedded_hal::spi;
+
+use super::dma::{configure_stream, transfer_len};
+use super::{Error, Event, Instance, Mode, Phase, PinMiso, PinMosi, PinNss, PinSck, Polarity};
+use crate::dma::traits::{Channel, DMASet, PeriAddress, Stream};
+use crate::dma::{ChannelX, MemoryToPeripheral, PeripheralToMemory};
+use crate::gpio::{Const, SetAlternate};
+use crate::pac::RCC;
+
+/// SPI slave, selected by the master through the NSS pin
+///
+/// The slave only shifts data while NSS is low. A byte has to be loaded with
+/// [`send`](spi::FullDuplex::send) before the master clocks it: preloading the first byte of a
+/// frame while NSS is high is the usual pattern. When the master clocks a byte the slave had no
+/// data for, the previous byte is sent again and the next `send` returns
+/// [`Error::Underrun`] without loading its byte.
+///
+/// At most two bytes wait for the master, in the data register and the shift register, so `send`
+/// blocks until `read` returns the byte received for one of them.
+pub struct SpiSlave<SPI, PINS> {
+    spi: SPI,
+    pins: PINS,
+    // Bytes loaded and not clocked out yet
+    loaded: u8,
+    underrun: bool,
+}
+
+impl<
+        SPI,
+        SCK,
+        MISO,
+        MOSI,
+        NSS,
+        const SCKA: u8,
+        const MISOA: u8,
+        const MOSIA: u8,
+        const NSSA: u8,
+    > SpiSlave<SPI, (SCK, MISO, MOSI, NSS)>
+where
+    SPI: Instance,
+    SCK: PinSck<SPI, A = Const<SCKA>> + SetAlternate<SCKA>,
+    MISO: PinMiso<SPI, A = Const<MISOA>> + SetAlternate<MISOA>,
+    MOSI: PinMosi<SPI, A = Const<MOSIA>> + SetAlternate<MOSIA>,
+    NSS: PinNss<SPI, A = Const<NSSA>> + SetAlternate<NSSA>,
+{
+    /// Creates a slave with 8 bit frames, MSB first, in `mode`
+    pub fn new(spi: SPI, mut pins: (SCK, MISO, MOSI, NSS), mode: Mode) -> Self {
+        unsafe {
+            // NOTE(unsafe) this reference will only be used for atomic writes with no side effects.
+            let rcc = &(*RCC::ptr());
+            SPI::enable(rcc);
+            SPI::reset(rcc);
+        }
+
+        pins.0.set_alt_mode();
+        pins.1.set_alt_mode();
+        pins.2.set_alt_mode();
+        pins.3.set_alt_mode();
+
+        spi.cr2.write(|w| w.ssoe().clear_bit());
+        spi.cr1.write(|w| {
+            w.cpha()
+                .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                .cpol()
+                .bit(mode.polarity == Polarity::IdleHigh)
+                // mstr: slave configuration
+                .mstr()
+                .clear_bit()
+                // lsbfirst: MSB first
+                .lsbfirst()
+                .clear_bit()
+                // ssm: the NSS pin selects the slave
+                .ssm()
+                .clear_bit()
+                .bidimode()
+                .clear_bit()
+                .rxonly()
+                .clear_bit()
+                // dff: 8 bit frames
+                .dff()
+                .clear_bit()
+                .spe()
+                .set_bit()
+        });
+
+        SpiSlave {
+            spi,
+            pins,
+            loaded: 0,
+            underrun: false,
+        }
+    }
+
+    pub fn release(mut self) -> (SPI, (SCK, MISO, MOSI, NSS)) {
+        self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+
+        self.pins.0.restore_mode();
+        self.pins.1.restore_mode();
+        self.pins.2.restore_mode();
+        self.pins.3.restore_mode();
+
+        (self.spi, self.pins)
+    }
+}
+
+impl<SPI, PINS> SpiSlave<SPI, PINS>
+where
+    SPI: Instance,
+{
+    /// Enable interrupts for the given `event`
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::Rxne => self.spi.cr2.modify(|_, w| w.rxneie().set_bit()),
+            Event::Txe => self.spi.cr2.modify(|_, w| w.txeie().set_bit()),
+            Event::Error => self.spi.cr2.modify(|_, w| w.errie().set_bit()),
+        }
+    }
+
+    /// Disable interrupts for the given `event`
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::Rxne => self.spi.cr2.modify(|_, w| w.rxneie().clear_bit()),
+            Event::Txe => self.spi.cr2.modify(|_, w| w.txeie().clear_bit()),
+            Event::Error => self.spi.cr2.modify(|_, w| w.errie().clear_bit()),
+        }
+    }
+
+    /// Return `true` if the TXE flag is set, i.e. a byte can be loaded
+    pub fn is_txe(&self) -> bool {
+        self.spi.sr.read().txe().bit_is_set()
+    }
+
+    /// Return `true` if the RXNE flag is set, i.e. a byte has been received
+    pub fn is_rxne(&self) -> bool {
+        self.spi.sr.read().rxne().bit_is_set()
+    }
+
+    /// Return `true` while a byte is being shifted, NSS is low
+    pub fn is_busy(&self) -> bool {
+        self.spi.sr.read().bsy().bit_is_set()
+    }
+
+    /// Receives a frame of `rx_buf.len()` bytes with DMA, while sending the byte preloaded with
+    /// `send` then the same byte again
+    ///
+    /// The transfer has to be started while NSS is high, between frames.
+    ///
+    /// # Panics
+    ///
+    /// * When `rx_buf` is empty or longer than 65535 bytes.
+    pub fn dma_read<RXSTREAM, RXBUF, const RXCH: u8>(
+        self,
+        rx_stream: RXSTREAM,
+        mut rx_buf: RXBUF,
+    ) -> SlaveDmaTransfer<SPI, PINS, (), RXSTREAM, (), RXBUF>
+    where
+        SPI: PeriAddress<MemSize = u8> + DMASet<RXSTREAM, PeripheralToMemory, RXCH>,
+        RXSTREAM: Stream,
+        ChannelX<RXCH>: Channel,
+        RXBUF: StaticWriteBuffer<Word = u8>,
+    {
+        // NOTE(unsafe) We now own the buffer and won't touch it until the transfer is over
+        let (rx_ptr, rx_len) = unsafe { rx_buf.write_buffer() };
+        let len = transfer_len(rx_len);
+
+        SlaveDmaTransfer::start::<RXCH>(self, (), rx_stream, (), rx_buf, rx_ptr as *mut u8, len)
+    }
+
+    /// Sends `tx_buf` while receiving a frame into `rx_buf` with DMA, both buffers must have the
+    /// same length
+    ///
+    /// The transfer has to be started while NSS is high, between frames.
+    ///
+    /// # Panics
+    ///
+    /// * When the buffer lengths differ.
+    /// * When the buffers are empty or longer than 65535 bytes.
+    pub fn dma_transfer<TXSTREAM, RXSTREAM, TXBUF, RXBUF, const TXCH: u8, const RXCH: u8>(
+        self,
+        mut tx_stream: TXSTREAM,
+        rx_stream: RXSTREAM,
+        tx_buf: TXBUF,
+        mut rx_buf: RXBUF,
+    ) -> SlaveDmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF>
+    where
+        SPI: PeriAddress<MemSize = u8>
+            + DMASet<TXSTREAM, MemoryToPeripheral, TXCH>
+            + DMASet<RXSTREAM, PeripheralToMemory, RXCH>,
+        TXSTREAM: Stream,
+        RXSTREAM: Stream,
+        ChannelX<TXCH>: Channel,
+        ChannelX<RXCH>: Channel,
+        TXBUF: StaticReadBuffer<Word = u8>,
+        RXBUF: StaticWriteBuffer<Word = u8>,
+    {
+        // NOTE(unsafe) We now own the buffers and won't touch them until the transfer is over
+        let (tx_ptr, tx_len) = unsafe { tx_buf.read_buffer() };
+        let (rx_ptr, rx_len) = unsafe { rx_buf.write_buffer() };
+        assert_eq!(tx_len, rx_len, "Buffer lengths differ");
+        let len = transfer_len(tx_len);
+
+        let address = self.spi.address();
+        configure_stream::<_, _, u8, TXCH>(
+            &mut tx_stream,
+            MemoryToPeripheral,
+            address,
+            (tx_ptr as u32, true),
+            len,
+        );
+
+        SlaveDmaTransfer::start::<RXCH>(
+            self,
+            tx_stream,
+            rx_stream,
+            tx_buf,
+            rx_buf,
+            rx_ptr as *mut u8,
+            len,
+        )
+    }
+}
+
+impl<SPI, PINS> spi::FullDuplex<u8> for SpiSlave<SPI, PINS>
+where
+    SPI: Instance,
+{
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        let sr = self.spi.sr.read();
+
+        if sr.ovr().bit_is_set() {
+            // Read DR then SR to clear the OVR bit
+            let _ = self.spi.dr.read();
+            let _ = self.spi.sr.read();
+            Err(nb::Error::Other(Error::Overrun))
+        } else if sr.rxne().bit_is_set() {
+            match self.loaded.checked_sub(1) {
+                Some(loaded) => self.loaded = loaded,
+                None => self.underrun = true,
+            }
+            // NOTE(read_volatile) read only 1 byte (the svd2rust API only allows reading a
+            // half-word)
+            Ok(unsafe { ptr::read_volatile(&self.spi.dr as *const _ as *const u8) })
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Loads a byte for the master to clock out
+    fn send(&mut self, byte: u8) -> nb::Result<(), Error> {
+        if self.underrun {
+            self.underrun = false;
+            return Err(nb::Error::Other(Error::Underrun));
+        }
+        // The data register and the shift register are both loaded
+        if self.loaded >= 2 {
+            return Err(nb::Error::WouldBlock);
+        }
+        if self.spi.sr.read().txe().bit_is_set() {
+            // NOTE(write_volatile) see note above
+            unsafe { ptr::write_volatile(self.spi.dr.as_ptr() as *mut u8, byte) }
+            self.loaded += 1;
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+pub(crate) mod sealed {
+    /// Transmit stream of a [`SlaveDmaTransfer`](super::SlaveDmaTransfer), `()` when there is
+    /// none
+    pub trait TxStream {
+        /// Enables the stream, returns false if there is none
+        fn enable(&mut self) -> bool;
+        /// Disables the stream and clears its flags
+        fn disable(&mut self);
+        /// Returns the number of bytes left to send
+        fn remaining(&self) -> u16;
+    }
+}
+use sealed::TxStream;
+
+impl TxStream for () {
+    fn enable(&mut self) -> bool {
+        false
+    }
+
+    fn disable(&mut self) {}
+
+    fn remaining(&self) -> u16 {
+        0
+    }
+}
+
+impl<STREAM: Stream> TxStream for STREAM {
+    fn enable(&mut self) -> bool {
+        // NOTE(unsafe) the stream was configured by `dma_transfer`
+        unsafe { Stream::enable(self) };
+        true
+    }
+
+    fn disable(&mut self) {
+        Stream::disable(self);
+        self.clear_interrupts();
+    }
+
+    fn remaining(&self) -> u16 {
+        STREAM::get_number_of_transfers()
+    }
+}
+
+/// A frame received by an SPI slave with DMA
+///
+/// Created by [`SpiSlave::dma_read`] or [`SpiSlave::dma_transfer`]. Dropping the transfer stops
+/// the streams.
+pub struct SlaveDmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF>
+where
+    SPI: Instance,
+    TXSTREAM: TxStream,
+    RXSTREAM: Stream,
+{
+    slave: SpiSlave<SPI, PINS>,
+    tx_stream: TXSTREAM,
+    rx_stream: RXSTREAM,
+    tx_buf: TXBUF,
+    rx_buf: RXBUF,
+}
+
+impl<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF>
+    SlaveDmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF>
+where
+    SPI: Instance + PeriAddress<MemSize = u8>,
+    TXSTREAM: TxStream,
+    RXSTREAM: Stream,
+{
+    #[allow(clippy::too_many_arguments)]
+    fn start<const RXCH: u8>(
+        slave: SpiSlave<SPI, PINS>,
+        mut tx_stream: TXSTREAM,
+        mut rx_stream: RXSTREAM,
+        tx_buf: TXBUF,
+        rx_buf: RXBUF,
+        rx_ptr: *mut u8,
+        len: u16,
+    ) -> Self
+    where
+        ChannelX<RXCH>: Channel,
+    {
+        let address = slave.spi.address();
+        configure_stream::<_, _, u8, RXCH>(
+            &mut rx_stream,
+            PeripheralToMemory,
+            address,
+            (rx_ptr as u32, true),
+            len,
+        );
+
+        // Drop a stale byte and the overrun flag it may have caused
+        let _ = slave.spi.dr.read();
+        let _ = slave.spi.sr.read();
+
+        // "Preceding reads and writes cannot be moved past subsequent writes"
+        compiler_fence(Ordering::Release);
+
+        slave.spi.cr2.modify(|_, w| w.rxdmaen().set_bit());
+        unsafe { Stream::enable(&mut rx_stream) };
+        if tx_stream.enable() {
+            slave.spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+        }
+
+        Self {
+            slave,
+            tx_stream,
+            rx_stream,
+            tx_buf,
+            rx_buf,
+        }
+    }
+
+    /// Returns true once the whole frame was received
+    pub fn is_complete(&self) -> bool {
+        RXSTREAM::get_transfer_complete_flag()
+    }
+
+    /// Checks the frame received so far
+    ///
+    /// Returns `Overrun` when a byte was lost, because the master clocked more bytes than the
+    /// frame or started before the transfer, and `Underrun` when the frame is complete but the
+    /// transmit stream couldn't load all its bytes in time.
+    pub fn check_errors(&self) -> Result<(), Error> {
+        if self.slave.spi.sr.read().ovr().bit_is_set() {
+            return Err(Error::Overrun);
+        }
+        if self.is_complete() && self.tx_stream.remaining() != 0 {
+            return Err(Error::Underrun);
+        }
+        Ok(())
+    }
+
+    /// Waits for the end of the frame, then returns the slave, the streams and the buffers
+    ///
+    /// The errors are left for [`check_errors`](Self::check_errors), to call before.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(mut self) -> (SpiSlave<SPI, PINS>, TXSTREAM, RXSTREAM, TXBUF, RXBUF) {
+        while !self.is_complete() {}
+        self.stop();
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads"
+        compiler_fence(Ordering::Acquire);
+
+        unsafe {
+            let slave = ptr::read(&self.slave);
+            let tx_stream = ptr::read(&self.tx_stream);
+            let rx_stream = ptr::read(&self.rx_stream);
+            let tx_buf = ptr::read(&self.tx_buf);
+            let rx_buf = ptr::read(&self.rx_buf);
+            mem::forget(self);
+            (slave, tx_stream, rx_stream, tx_buf, rx_buf)
+        }
+    }
+
+    fn stop(&mut self) {
+        self.slave
+            .spi
+            .cr2
+            .modify(|_, w| w.txdmaen().clear_bit().rxdmaen().clear_bit());
+        self.tx_stream.disable();
+        Stream::disable(&mut self.rx_stream);
+        self.rx_stream.clear_interrupts();
+    }
+}
+
+impl<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF> Drop
+    for SlaveDmaTransfer<SPI, PINS, TXSTREAM, RXSTREAM, TXBUF, RXBUF>
+where
+    SPI: Instance,
+    TXSTREAM: TxStream,
+    RXSTREAM: Stream,
+{
+    fn drop(&mut self) {
+        self.slave
+            .spi
+            .cr2
+            .modify(|_, w| w.txdmaen().clear_bit().rxdmaen().clear_bit());
+        self.tx_stream.disable();
+        Stream::disable(&mut self.rx_stream);
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -325,6 +325,7 @@ hal! {
     crate::pac::TIM14: (tim14),
 }
 
+#[allow(unused)]
 use crate::gpio::gpiod::*;
 #[allow(unused)]
 use crate::gpio::gpioe::*;
@@ -354,7 +355,8 @@ macro_rules! channel_impl {
 // The approach to PWM channel implementation is to group parts with
 // common pins, starting with groupings of the largest number of parts
 // and moving to smaller and smaller groupings.  Last, we have individual
-// parts to cover exceptions.
+// parts to cover exceptions. Pins that are not bonded out on every package
+// are grouped by the smallest package that has them.
 
 // All parts have these PWM pins.
 channel_impl!(
@@ -375,11 +377,6 @@ channel_impl!(
 );
 
 channel_impl!(
-    TIM1, PinC1, PE9, 1;
-    TIM1, PinC2, PE11, 1;
-    TIM1, PinC3, PE13, 1;
-    TIM1, PinC4, PE14, 1;
-
     TIM2, PinC1, PA0, 1;
     TIM2, PinC2, PA1, 1;
     TIM2, PinC3, PA2, 1;
@@ -410,39 +407,59 @@ channel_impl!(
     TIM4, PinC3, PB8, 2;
     TIM4, PinC4, PB9, 2;
 
+    TIM10, PinC1, PB8, 3;
+);
+
+channel_impl!(
+    TIM8, PinC1, PC6, 3;
+    TIM8, PinC2, PC7, 3;
+    TIM8, PinC3, PC8, 3;
+    TIM8, PinC4, PC9, 3;
+
+    TIM12, PinC1, PB14, 9;
+    TIM12, PinC2, PB15, 9;
+
+    TIM13, PinC1, PA6, 9;  // Not a mistake: TIM13 has only one channel.
+
+    TIM14, PinC1, PA7, 9;  // Not a mistake: TIM14 has only one channel.
+);
+
+// Pins on GPIOD and GPIOE, bonded out on LQFP100 and larger packages.
+#[cfg(not(feature = "lqfp64"))]
+channel_impl!(
+    TIM1, PinC1, PE9, 1;
+    TIM1, PinC2, PE11, 1;
+    TIM1, PinC3, PE13, 1;
+    TIM1, PinC4, PE14, 1;
+
     TIM4, PinC1, PD12, 2;
     TIM4, PinC2, PD13, 2;
     TIM4, PinC3, PD14, 2;
     TIM4, PinC4, PD15, 2;
 
-    TIM10, PinC1, PB8, 3;
-);
-
-channel_impl!(
     TIM9, PinC1, PE5, 3;
     TIM9, PinC2, PE6, 3;
 );
 
+// Pins on GPIOF and GPIOG, bonded out on LQFP144 and larger packages.
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100")))]
 channel_impl!(
-    TIM8, PinC1, PC6, 3;
-    TIM8, PinC2, PC7, 3;
-    TIM8, PinC3, PC8, 3;
-    TIM8, PinC4, PC9, 3;
-
     TIM10, PinC1, PF6, 3;
 
     TIM11, PinC1, PF7, 3;
 
-    TIM12, PinC1, PB14, 9;
-    TIM12, PinC2, PB15, 9;
+    TIM13, PinC1, PF8, 9;
 
-    TIM13, PinC1, PA6, 9;
-    TIM13, PinC1, PF8, 9;  // Not a mistake: TIM13 has only one channel.
+    TIM14, PinC1, PF9, 9;
 
-    TIM14, PinC1, PA7, 9;
-    TIM14, PinC1, PF9, 9;  // Not a mistake: TIM14 has only one channel.
+    TIM5, PinC1, PF3, 2;
+    TIM5, PinC2, PF4, 2;
+    TIM5, PinC3, PF5, 2;
+    TIM5, PinC4, PF10, 2;
 );
 
+// Pins on GPIOH (PH2 and up) and GPIOI, only bonded out on 176 pin packages.
+#[cfg(not(any(feature = "lqfp64", feature = "lqfp100", feature = "lqfp144")))]
 channel_impl!(
     TIM5, PinC1, PH10, 2;
     TIM5, PinC2, PH11, 2;
@@ -457,10 +474,3 @@ channel_impl!(
     TIM12, PinC1, PH6, 9;
     TIM12, PinC2, PH9, 9;
 );
-
-channel_impl!(
-    TIM5, PinC1, PF3, 2;
-    TIM5, PinC2, PF4, 2;
-    TIM5, PinC3, PF5, 2;
-    TIM5, PinC4, PF10, 2;
-);